@@ -1,4 +1,457 @@
-pub const TICK_INTERVAL_MS: u64 = 100; // 100 ms
-pub const DURATION_SEC: i64 = 6000; // last 6000 seconds
-pub const INGESTION_BUFFER_SIZE: usize = 100_000; // Buffer size for ingestion channel
+use std::{
+    collections::HashSet, fmt::Display, net::SocketAddr, path::PathBuf, str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use clap::Parser;
+use serde::{Deserialize, Deserializer, de::Error as _};
+use ticker_core::{
+    aggregation::AggregationMethod,
+    consolidation::ConsolidationMethod,
+    error::TickerError,
+    ingestion::{OverflowPolicy, TimeMode},
+    registry::ExchangeRegistry,
+    retention::{RetentionPolicy, RetentionTier},
+    supervisor::{RestartPolicy, SupervisorConfig},
+    types::{Pair, Resolution},
+};
+
+pub const TICKS_DEFAULT_LIMIT: usize = 1_000; // Page size of /ticks when no limit is given
+pub const TICKS_MAX_LIMIT: usize = 10_000; // Largest page /ticks will return
+pub const CANDLES_DEFAULT_INTERVAL: &str = "1m"; // Bar width of /candles when no interval is given
+pub const CANDLES_DEFAULT_COUNT: usize = 500; // Bars /candles covers when no from is given
+pub const CANDLES_MAX_COUNT: usize = 5_000; // Most bars one /candles range may span
+pub const CANDLES_TICKS_PAGE_SIZE: usize = 10_000; // Ticks read per query when /candles builds bars from ticks
 pub const INTERNAL_CHANNEL_SIZE: usize = 1024; // Internal channel size for communication between tasks
+pub const LIVE_CHANNEL_SIZE: usize = 1024; // Ticks buffered per live subscriber before it starts skipping
+pub const MAX_ALLOWED_LATENESS_MS: u64 = 3_600_000; // Longest event-time buckets may be held open
+
+/// Serves aggregated exchange prices. Every flag can also be set through the
+/// environment variable shown next to it; both override the config file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML config file; built-in defaults are used without one.
+    #[arg(short, long, env = "TICKER_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "TICKER_BIND")]
+    pub bind: Option<SocketAddr>,
+    #[arg(long, env = "TICKER_DATABASE_URL")]
+    pub database_url: Option<String>,
+    /// Bearer token required by the `/admin` endpoints.
+    #[arg(long, env = "TICKER_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+    /// `<exchange>:<pair>` to ingest, replacing the subscriptions of the file.
+    #[arg(
+        long = "subscribe",
+        env = "TICKER_SUBSCRIPTIONS",
+        value_delimiter = ','
+    )]
+    pub subscriptions: Vec<Subscription>,
+    #[arg(long, env = "TICKER_AGGREGATION")]
+    pub aggregation: Option<AggregationMethod>,
+    #[arg(long, env = "TICKER_TICK_INTERVAL_MS")]
+    pub tick_interval_ms: Option<u64>,
+    /// `<resolution>:<keep for>` tiers, finest first, replacing those of the file.
+    #[arg(long, env = "TICKER_RETENTION", value_delimiter = ',')]
+    pub retention: Vec<RetentionTier>,
+    /// `never`, `always` or `on-failure:<max retries>` for background tasks.
+    #[arg(long, env = "TICKER_RESTART")]
+    pub restart: Option<RestartPolicy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: SocketAddr,
+    /// `sqlite:`, `memory:` or `postgres://` (with the postgres feature).
+    pub database_url: String,
+    /// Bearer token required by `/admin`. May only be left unset when
+    /// `bind` is a loopback address, which leaves `/admin` open locally.
+    pub admin_token: Option<String>,
+    /// (exchange id, pair) ingestion tasks started at boot; more can be added
+    /// through `/admin/subscriptions`.
+    pub subscriptions: Vec<Subscription>,
+    /// Reference price computed per tick.
+    #[serde(deserialize_with = "from_str")]
+    pub aggregation: AggregationMethod,
+    pub ingestion: IngestionSettings,
+    pub consolidation: ConsolidationSettings,
+    pub retention: RetentionSettings,
+    pub storage: StorageSettings,
+    pub supervisor: SupervisorSettings,
+}
+
+/// One `<exchange>:<pair>` ingestion task, a `{ exchange, pair }` table in TOML.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subscription {
+    pub exchange: String,
+    #[serde(deserialize_with = "from_str")]
+    pub pair: Pair,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestionSettings {
+    pub tick_interval_ms: u64,
+    /// Events buffered per feed between ticks before `overflow` applies.
+    pub buffer_size: usize,
+    /// `drop_oldest`, `drop_newest`, `early_flush` or `pre_aggregate`.
+    #[serde(deserialize_with = "from_str")]
+    pub overflow: OverflowPolicy,
+    pub reconnect_initial_backoff_ms: u64,
+    pub reconnect_max_backoff_ms: u64,
+    /// Consecutive failures before giving up, unset retries forever.
+    pub reconnect_max_retries: Option<u32>,
+    /// Mark a connected feed stale after this long without trades.
+    pub stale_after_ms: u64,
+    /// OHLCV bars built from raw trades.
+    #[serde(deserialize_with = "from_strs")]
+    pub candle_resolutions: Vec<Resolution>,
+    /// Bucket by exchange timestamp with this much lateness; unset buckets
+    /// by arrival on the local clock.
+    pub allowed_lateness_ms: Option<u64>,
+    /// Recent trade ids remembered per feed to drop replays.
+    pub dedup_window: usize,
+    /// Persist every trade to the trades table.
+    pub store_raw_trades: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsolidationSettings {
+    pub enabled: bool,
+    /// Synthetic exchange serving the cross-venue price.
+    pub exchange: String,
+    #[serde(deserialize_with = "from_str")]
+    pub method: ConsolidationMethod,
+    /// Leave out venues without a tick for this long.
+    pub stale_after_ms: u64,
+    /// Leave out venues further than this fraction from the median.
+    pub max_deviation: f64,
+    /// Fewest venues needed to emit a consolidated tick.
+    pub min_venues: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSettings {
+    /// `<resolution>:<keep for>` tiers, finest first; older ticks roll up
+    /// into the next. The first one is the raw tick interval.
+    #[serde(deserialize_with = "from_strs")]
+    pub tiers: Vec<RetentionTier>,
+    pub interval_sec: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Max rows written per transaction.
+    pub batch_size: usize,
+    /// Max time a row waits before its batch is written.
+    pub batch_latency_ms: u64,
+    /// Retries of a failed write before the storage task fails.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled per failure.
+    pub retry_delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SupervisorSettings {
+    /// `never`, `always` or `on-failure:<max retries>`, applied to ingestion,
+    /// storage, retention and consolidation tasks.
+    #[serde(deserialize_with = "from_str")]
+    pub restart: RestartPolicy,
+    /// Delay before a restart, doubled per consecutive failure.
+    pub restart_delay_ms: u64,
+    pub max_restart_delay_ms: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            database_url: "sqlite:./db/prices.db".to_string(),
+            admin_token: None,
+            subscriptions: ["Binance", "Bybit", "Coinbase"]
+                .into_iter()
+                .map(|exchange| Subscription {
+                    exchange: exchange.to_string(),
+                    pair: Pair::new("SOL", "USDT"),
+                })
+                .collect(),
+            aggregation: AggregationMethod::Vwap,
+            ingestion: IngestionSettings::default(),
+            consolidation: ConsolidationSettings::default(),
+            retention: RetentionSettings::default(),
+            storage: StorageSettings::default(),
+            supervisor: SupervisorSettings::default(),
+        }
+    }
+}
+
+impl Default for IngestionSettings {
+    fn default() -> Self {
+        Self {
+            tick_interval_ms: 100,
+            buffer_size: 100_000,
+            overflow: OverflowPolicy::default(),
+            reconnect_initial_backoff_ms: 500,
+            reconnect_max_backoff_ms: 30_000,
+            reconnect_max_retries: None,
+            stale_after_ms: 10_000,
+            candle_resolutions: ["1s", "1m", "5m", "1h"]
+                .into_iter()
+                .filter_map(|resolution| resolution.parse().ok())
+                .collect(),
+            allowed_lateness_ms: None,
+            dedup_window: 10_000,
+            store_raw_trades: false,
+        }
+    }
+}
+
+impl Default for ConsolidationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            exchange: "Consolidated".to_string(),
+            method: ConsolidationMethod::VolumeWeighted,
+            stale_after_ms: 5_000,
+            max_deviation: 0.02,
+            min_venues: 1,
+        }
+    }
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            tiers: ["100ms:1d", "1s:30d", "1m"]
+                .into_iter()
+                .filter_map(|tier| tier.parse().ok())
+                .collect(),
+            interval_sec: 300,
+        }
+    }
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            batch_latency_ms: 250,
+            max_retries: 5,
+            retry_delay_ms: 500,
+        }
+    }
+}
+
+impl Default for SupervisorSettings {
+    fn default() -> Self {
+        Self {
+            restart: RestartPolicy::default(),
+            restart_delay_ms: 1_000,
+            max_restart_delay_ms: 60_000,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the command line, environment and config file.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::from_cli(Cli::parse())
+    }
+
+    pub fn from_cli(cli: Cli) -> anyhow::Result<Self> {
+        let mut config = match &cli.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Invalid config {}", path.display()))?
+            }
+            None => Self::default(),
+        };
+
+        if let Some(bind) = cli.bind {
+            config.bind = bind;
+        }
+        if let Some(database_url) = cli.database_url {
+            config.database_url = database_url;
+        }
+        if let Some(admin_token) = cli.admin_token {
+            config.admin_token = Some(admin_token);
+        }
+        if !cli.subscriptions.is_empty() {
+            config.subscriptions = cli.subscriptions;
+        }
+        if let Some(aggregation) = cli.aggregation {
+            config.aggregation = aggregation;
+        }
+        if let Some(tick_interval_ms) = cli.tick_interval_ms {
+            config.ingestion.tick_interval_ms = tick_interval_ms;
+        }
+        if !cli.retention.is_empty() {
+            config.retention.tiers = cli.retention;
+        }
+        if let Some(restart) = cli.restart {
+            config.supervisor.restart = restart;
+        }
+
+        Ok(config)
+    }
+
+    /// Checks the settings against each other and against the exchanges the
+    /// registry knows, before anything is started.
+    pub fn validate(&self, registry: &ExchangeRegistry) -> anyhow::Result<()> {
+        if self.admin_token.as_deref().is_some_and(str::is_empty) {
+            bail!("admin_token must not be empty");
+        }
+        if self.admin_token.is_none() && !self.bind.ip().is_loopback() {
+            bail!(
+                "admin_token must be set to bind to {}, or /admin would be open",
+                self.bind
+            );
+        }
+
+        let ingestion = &self.ingestion;
+        if ingestion.tick_interval_ms == 0 {
+            bail!("ingestion.tick_interval_ms must be positive");
+        }
+        if ingestion.buffer_size == 0 {
+            bail!("ingestion.buffer_size must be positive");
+        }
+        if ingestion
+            .allowed_lateness_ms
+            .is_some_and(|ms| ms > MAX_ALLOWED_LATENESS_MS)
+        {
+            bail!(
+                "ingestion.allowed_lateness_ms must be at most {}",
+                MAX_ALLOWED_LATENESS_MS
+            );
+        }
+        if ingestion.reconnect_initial_backoff_ms > ingestion.reconnect_max_backoff_ms {
+            bail!("ingestion.reconnect_initial_backoff_ms exceeds reconnect_max_backoff_ms");
+        }
+        if self.storage.batch_size == 0 {
+            bail!("storage.batch_size must be positive");
+        }
+        if self.supervisor.restart_delay_ms > self.supervisor.max_restart_delay_ms {
+            bail!("supervisor.restart_delay_ms exceeds max_restart_delay_ms");
+        }
+
+        let mut seen = HashSet::new();
+        for subscription in &self.subscriptions {
+            let info = registry
+                .get(&subscription.exchange)
+                .filter(|_| registry.adapter(&subscription.exchange).is_ok())
+                .with_context(|| {
+                    format!(
+                        "Unknown exchange in subscriptions: {}",
+                        subscription.exchange
+                    )
+                })?;
+            if !info.supports(&subscription.pair) {
+                bail!(
+                    "{} does not list {}",
+                    subscription.exchange,
+                    subscription.pair
+                );
+            }
+            if !seen.insert(subscription) {
+                bail!("Duplicate subscription {}", subscription);
+            }
+        }
+
+        let consolidation = &self.consolidation;
+        if consolidation.enabled {
+            if registry.get(&consolidation.exchange).is_some() {
+                bail!(
+                    "consolidation.exchange {} clashes with a real exchange",
+                    consolidation.exchange
+                );
+            }
+            if !consolidation.max_deviation.is_finite() || consolidation.max_deviation < 0.0 {
+                bail!("consolidation.max_deviation must be a non-negative fraction");
+            }
+        }
+
+        let raw_resolution = self.retention_policy()?.tiers()[0].resolution;
+        if raw_resolution.as_millis() as u64 != ingestion.tick_interval_ms {
+            bail!(
+                "First retention tier {} must match ingestion.tick_interval_ms ({}ms)",
+                raw_resolution,
+                ingestion.tick_interval_ms
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn retention_policy(&self) -> Result<RetentionPolicy, TickerError> {
+        RetentionPolicy::new(self.retention.tiers.clone())
+    }
+
+    pub fn supervisor_config(&self) -> SupervisorConfig {
+        SupervisorConfig {
+            restart_delay: Duration::from_millis(self.supervisor.restart_delay_ms),
+            max_restart_delay: Duration::from_millis(self.supervisor.max_restart_delay_ms),
+        }
+    }
+
+    pub fn time_mode(&self) -> TimeMode {
+        match self.ingestion.allowed_lateness_ms {
+            Some(ms) => TimeMode::EventTime {
+                allowed_lateness: Duration::from_millis(ms),
+            },
+            None => TimeMode::WallClock,
+        }
+    }
+}
+
+impl FromStr for Subscription {
+    type Err = TickerError;
+
+    /// Parses `<exchange>:<pair>` such as `Binance:SOLUSDT`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (exchange, pair) = s
+            .split_once(':')
+            .ok_or_else(|| TickerError::InvalidArgument(format!("Invalid subscription: {}", s)))?;
+        Ok(Self {
+            exchange: exchange.trim().to_string(),
+            pair: pair.trim().parse()?,
+        })
+    }
+}
+
+impl std::fmt::Display for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.exchange, self.pair)
+    }
+}
+
+/// Deserializes a value from its string form.
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err: Display>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(D::Error::custom)
+}
+
+fn from_strs<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err: Display>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| s.parse().map_err(D::Error::custom))
+        .collect()
+}
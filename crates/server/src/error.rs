@@ -0,0 +1,92 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use ticker_core::error::TickerError;
+
+/// Error returned by the JSON endpoints as `{"error": ..., "message": ...}`.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody<'a> {
+    error: &'a str,
+    message: &'a str,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    /// Rejects a request whose parameters failed to parse or validate.
+    pub fn invalid_input(err: TickerError) -> Self {
+        Self::bad_request(err.to_string())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            message: message.into(),
+        }
+    }
+}
+
+/// Errors that reach a handler unmapped come from storage or decoding, not
+/// from the request; handlers map client input errors with
+/// [`ApiError::invalid_input`].
+impl From<TickerError> for ApiError {
+    fn from(err: TickerError) -> Self {
+        eprintln!("Internal error: {}", err);
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "Internal server error".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let error = self
+            .status
+            .canonical_reason()
+            .unwrap_or("Error")
+            .to_lowercase()
+            .replace(' ', "_");
+        let body = ApiErrorBody {
+            error: &error,
+            message: &self.message,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
@@ -1,61 +1,242 @@
 use std::{sync::Arc, time::Duration};
 
-use sqlx::SqlitePool;
 use ticker_core::{
-    adapters::{BinanceAdapter, BybitAdapter, CoinbaseAdapter},
-    types::PriceTick,
+    consolidation::{ConsolidationConfig, run_consolidation_task},
+    ingestion::{IngestionConfig, IngestionSinks, ReconnectPolicy},
+    registry::ExchangeRegistry,
+    storage::BatchConfig,
+    supervisor::Supervisor,
+    types::{Candle, Exchange, PriceTick, Trade},
 };
-use tokio::{sync::mpsc, task::JoinSet};
+use tokio::sync::{Mutex, broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
 
 pub mod config;
+pub mod error;
 pub mod server;
 pub mod services;
 pub mod ui;
 
-const PORT: u16 = 3000;
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let db = SqlitePool::connect("sqlite:./db/prices.db")
+    let config = config::Config::load()?;
+    let mut registry = ExchangeRegistry::with_defaults();
+    config.validate(&registry)?;
+
+    let tick = Duration::from_millis(config.ingestion.tick_interval_ms);
+    let candle_resolutions = config.ingestion.candle_resolutions.clone();
+    let consolidation = config.consolidation.enabled.then(|| ConsolidationConfig {
+        exchange: Exchange::new(&config.consolidation.exchange),
+        method: config.consolidation.method,
+        stale_after: Duration::from_millis(config.consolidation.stale_after_ms),
+        max_deviation: config.consolidation.max_deviation,
+        min_venues: config.consolidation.min_venues,
+        tick,
+        candle_resolutions: candle_resolutions.clone(),
+        buffer_size: config.ingestion.buffer_size,
+    });
+    if let Some(consolidation) = &consolidation {
+        registry.register(consolidation.info());
+    }
+    let registry = Arc::new(registry);
+    let store = ticker_core::storage::connect(&config.database_url)
         .await
         .expect("Failed to connect to database");
 
     let (tx, rx) = mpsc::channel::<PriceTick>(config::INTERNAL_CHANNEL_SIZE);
-    let tick = Duration::from_millis(config::TICK_INTERVAL_MS);
-
-    let mut set = JoinSet::new();
-    set.spawn(ticker_core::storage::run_db_task(db.clone(), rx));
-    set.spawn(ticker_core::ingestion::run_ingestion_task(
-        tx.clone(),
-        BinanceAdapter,
-        ticker_core::types::Pair::SOLUSDT,
-        config::INGESTION_BUFFER_SIZE,
-        tick,
-    ));
-    set.spawn(ticker_core::ingestion::run_ingestion_task(
-        tx.clone(),
-        BybitAdapter,
-        ticker_core::types::Pair::SOLUSDT,
-        config::INGESTION_BUFFER_SIZE,
-        tick,
-    ));
-    set.spawn(ticker_core::ingestion::run_ingestion_task(
-        tx.clone(),
-        CoinbaseAdapter,
-        ticker_core::types::Pair::SOLUSDT,
-        config::INGESTION_BUFFER_SIZE,
+    let (candle_tx, candle_rx) = mpsc::channel::<Candle>(config::INTERNAL_CHANNEL_SIZE);
+    let (live_tx, _) = broadcast::channel::<PriceTick>(config::LIVE_CHANNEL_SIZE);
+    let mut sinks = IngestionSinks::new(tx.clone())
+        .with_live(live_tx.clone())
+        .with_candles(candle_tx.clone());
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let supervisor = Arc::new(Supervisor::new(config.supervisor_config()));
+    let restart = config.supervisor.restart;
+    let ingestion_config = IngestionConfig {
+        buffer_size: config.ingestion.buffer_size,
+        overflow: config.ingestion.overflow,
         tick,
+        reconnect: ReconnectPolicy {
+            initial_backoff: Duration::from_millis(config.ingestion.reconnect_initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.ingestion.reconnect_max_backoff_ms),
+            max_retries: config.ingestion.reconnect_max_retries,
+            ..Default::default()
+        },
+        stale_after: Duration::from_millis(config.ingestion.stale_after_ms),
+        aggregator: config.aggregation.build(),
+        candle_resolutions: candle_resolutions.clone(),
+        time_mode: config.time_mode(),
+        dedup_window: config.ingestion.dedup_window,
+        restart,
+    };
+
+    let retention = config
+        .retention_policy()?
+        .with_interval(Duration::from_secs(config.retention.interval_sec))
+        .with_aggregator(config.aggregation.build());
+
+    let batch = BatchConfig {
+        max_size: config.storage.batch_size,
+        max_latency: Duration::from_millis(config.storage.batch_latency_ms),
+        max_retries: config.storage.max_retries,
+        retry_delay: Duration::from_millis(config.storage.retry_delay_ms),
+    };
+
+    // Receivers sit behind a mutex so a restarted storage task picks up the
+    // same channel. Storage tasks are stopped last so they can drain it.
+    let mut storage_tasks = Vec::new();
+    if config.ingestion.store_raw_trades {
+        let (trade_tx, trade_rx) = mpsc::channel::<Trade>(config::INTERNAL_CHANNEL_SIZE);
+        sinks = sinks.with_trades(trade_tx);
+        storage_tasks.push(supervisor.spawn("trade storage", restart, {
+            let (store, batch, rx) = (store.clone(), batch.clone(), Arc::new(Mutex::new(trade_rx)));
+            move |cancel| {
+                let (store, batch, rx) = (store.clone(), batch.clone(), rx.clone());
+                async move {
+                    ticker_core::storage::run_trade_db_task(
+                        store,
+                        &mut *rx.lock().await,
+                        batch,
+                        cancel,
+                    )
+                    .await
+                }
+            }
+        }));
+    }
+    let consolidation_task = consolidation.map(|consolidation| {
+        let sinks = IngestionSinks::new(tx)
+            .with_live(live_tx.clone())
+            .with_candles(candle_tx);
+        let live_tx = live_tx.clone();
+        supervisor.spawn("consolidation", restart, move |cancel| {
+            run_consolidation_task(
+                sinks.clone(),
+                live_tx.subscribe(),
+                consolidation.clone(),
+                cancel,
+            )
+        })
+    });
+    storage_tasks.push(supervisor.spawn("tick storage", restart, {
+        let (store, batch, rx) = (store.clone(), batch.clone(), Arc::new(Mutex::new(rx)));
+        move |cancel| {
+            let (store, batch, rx) = (store.clone(), batch.clone(), rx.clone());
+            async move {
+                ticker_core::storage::run_db_task(store, &mut *rx.lock().await, batch, cancel).await
+            }
+        }
+    }));
+    storage_tasks.push(supervisor.spawn("candle storage", restart, {
+        let (store, rx) = (store.clone(), Arc::new(Mutex::new(candle_rx)));
+        move |cancel| {
+            let (store, batch, rx) = (store.clone(), batch.clone(), rx.clone());
+            async move {
+                ticker_core::storage::run_candle_db_task(
+                    store,
+                    &mut *rx.lock().await,
+                    batch,
+                    cancel,
+                )
+                .await
+            }
+        }
+    }));
+    let retention_task = supervisor.spawn("retention", restart, {
+        let (store, retention) = (store.clone(), retention.clone());
+        move |cancel| {
+            ticker_core::retention::run_retention_task(store.clone(), retention.clone(), cancel)
+        }
+    });
+    let feed_service = Arc::new(services::FeedService::new(
+        registry.clone(),
+        supervisor.clone(),
+        sinks,
+        ingestion_config,
+        Some(events_tx),
     ));
+    for subscription in &config.subscriptions {
+        feed_service.subscribe(&subscription.exchange, subscription.pair.clone())?;
+    }
+    tokio::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            println!("Ingestion: {}", event);
+        }
+    });
 
-    let price_service = services::PriceService { db };
+    let price_service = services::PriceService {
+        store,
+        registry: registry.clone(),
+        retention,
+        candle_resolutions,
+    };
+    let shutdown = CancellationToken::new();
+    let live_service = services::LiveService {
+        ticks: live_tx,
+        registry,
+        shutdown: shutdown.clone(),
+    };
     let app_state = server::AppState {
         price: Arc::new(price_service),
+        feeds: feed_service.clone(),
+        tasks: supervisor,
+        live: Arc::new(live_service),
+        admin_token: config.admin_token.as_deref().map(Arc::from),
     };
     let app = server::create_app(app_state);
-    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], PORT));
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let listener = tokio::net::TcpListener::bind(config.bind).await?;
+
+    println!("Server running on http://{}", config.bind);
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            println!("Shutting down, flushing buffered ticks...");
+            shutdown.cancel();
+        }
+    });
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
 
-    println!("Server running on http://{}", addr);
-    axum::serve(listener, app).await?;
+    // Producers first, so everything they flush reaches storage.
+    feed_service.shutdown().await;
+    if let Some(task) = consolidation_task {
+        task.shutdown().await;
+    }
+    retention_task.shutdown().await;
+    for task in storage_tasks {
+        task.shutdown().await;
+    }
+    println!("Shutdown complete");
     Ok(())
 }
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                eprintln!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
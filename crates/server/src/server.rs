@@ -1,31 +1,240 @@
-use std::sync::Arc;
+use std::{convert::Infallible, sync::Arc};
 
-use axum::{Json, extract::State, http::StatusCode, routing::get};
-use ticker_core::types::PriceTick;
+use axum::{
+    Json,
+    extract::{
+        Path, Query, Request, State,
+        rejection::{JsonRejection, QueryRejection},
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{StatusCode, header::AUTHORIZATION},
+    middleware::{self, Next},
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{delete, get, post},
+};
+use ticker_core::{
+    registry::ExchangeInfo,
+    storage::TickPage,
+    supervisor::{Supervisor, TaskState},
+    types::{Candle, PriceTick},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
+use tokio_util::sync::CancellationToken;
 
-use crate::{services::PriceService, ui::index_page};
+use crate::{
+    error::ApiError,
+    services::{
+        CandlesParams, FeedReport, FeedService, LiveFilter, LiveParams, LiveService, PriceService,
+        SubscriptionRequest, TicksParams,
+    },
+    ui::index_page,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub price: Arc<PriceService>,
+    pub feeds: Arc<FeedService>,
+    /// Background tasks and their restart state.
+    pub tasks: Arc<Supervisor>,
+    pub live: Arc<LiveService>,
+    /// Bearer token required by `/admin`, which is open when unset; config
+    /// validation only allows that on a loopback bind.
+    pub admin_token: Option<Arc<str>>,
 }
 
 pub fn create_app(state: AppState) -> axum::Router {
+    let admin = axum::Router::new()
+        .route("/subscriptions", get(get_status).post(add_subscription))
+        .route(
+            "/subscriptions/{exchange}/{pair}",
+            delete(remove_subscription),
+        )
+        .route(
+            "/subscriptions/{exchange}/{pair}/pause",
+            post(pause_subscription),
+        )
+        .route(
+            "/subscriptions/{exchange}/{pair}/resume",
+            post(resume_subscription),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
     axum::Router::new()
         .route("/ticks", get(get_ticks))
+        .route("/candles", get(get_candles))
+        .route("/exchanges", get(get_exchanges))
+        .route("/status", get(get_status))
+        .route("/tasks", get(get_tasks))
+        .route("/ws", get(ws_ticks))
+        .route("/sse", get(sse_ticks))
         .route("/ui", get(index_page))
+        .nest("/admin", admin)
         .with_state(state)
 }
 
-pub async fn get_ticks(State(state): State<AppState>) -> Result<Json<Vec<PriceTick>>, StatusCode> {
-    println!("Received request for price ticks");
-    let res = state.price.get_ticks().await.map(Json);
+pub async fn get_ticks(
+    State(state): State<AppState>,
+    params: Result<Query<TicksParams>, QueryRejection>,
+) -> Result<Json<TickPage>, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let query = state
+        .price
+        .tick_query(&params)
+        .map_err(ApiError::invalid_input)?;
+    Ok(Json(state.price.get_ticks(&query).await?))
+}
+
+pub async fn get_candles(
+    State(state): State<AppState>,
+    params: Result<Query<CandlesParams>, QueryRejection>,
+) -> Result<Json<Vec<Candle>>, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let query = state
+        .price
+        .candle_query(&params)
+        .map_err(ApiError::invalid_input)?;
+    Ok(Json(state.price.get_candles(&query).await?))
+}
+
+pub async fn get_exchanges(State(state): State<AppState>) -> Json<Vec<ExchangeInfo>> {
+    Json(state.price.get_exchanges())
+}
+
+pub async fn get_status(State(state): State<AppState>) -> Json<Vec<FeedReport>> {
+    Json(state.feeds.get_status())
+}
+
+pub async fn get_tasks(State(state): State<AppState>) -> Json<Vec<TaskState>> {
+    Json(state.tasks.states())
+}
+
+async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if let Some(token) = &state.admin_token {
+        let bearer = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer != Some(&**token) {
+            return Err(ApiError::unauthorized("Missing or invalid admin token"));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+pub async fn add_subscription(
+    State(state): State<AppState>,
+    body: Result<Json<SubscriptionRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<FeedReport>), ApiError> {
+    let Json(body) = body.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let pair = body.pair.parse().map_err(ApiError::invalid_input)?;
+    let report = state.feeds.subscribe(&body.exchange, pair)?;
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+pub async fn pause_subscription(
+    State(state): State<AppState>,
+    Path((exchange, pair)): Path<(String, String)>,
+) -> Result<Json<FeedReport>, ApiError> {
+    Ok(Json(state.feeds.pause(&exchange, &pair)?))
+}
+
+pub async fn resume_subscription(
+    State(state): State<AppState>,
+    Path((exchange, pair)): Path<(String, String)>,
+) -> Result<Json<FeedReport>, ApiError> {
+    Ok(Json(state.feeds.resume(&exchange, &pair)?))
+}
 
-    match res {
-        Ok(json) => Ok(json),
-        Err(e) => {
-            eprintln!("Error fetching price ticks: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+pub async fn remove_subscription(
+    State(state): State<AppState>,
+    Path((exchange, pair)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    state.feeds.unsubscribe(&exchange, &pair)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Pushes every matching tick as a JSON text message.
+pub async fn ws_ticks(
+    State(state): State<AppState>,
+    params: Result<Query<LiveParams>, QueryRejection>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let filter = state
+        .live
+        .filter(&params)
+        .map_err(ApiError::invalid_input)?;
+    let rx = state.live.subscribe();
+    let shutdown = state.live.shutdown.clone();
+    Ok(ws.on_upgrade(move |socket| stream_ws(socket, rx, filter, shutdown)))
+}
+
+async fn stream_ws(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<PriceTick>,
+    filter: LiveFilter,
+    shutdown: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            tick = rx.recv() => match tick {
+                Ok(tick) if filter.matches(&tick) => {
+                    let Ok(json) = serde_json::to_string(&tick) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(json.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!("WebSocket client lagging, skipped {} ticks", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = shutdown.cancelled() => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
         }
     }
 }
+
+/// Pushes every matching tick as a JSON `data` event. A `lagged` event
+/// carries the number of ticks skipped when the client falls behind.
+pub async fn sse_ticks(
+    State(state): State<AppState>,
+    params: Result<Query<LiveParams>, QueryRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+    let filter = state
+        .live
+        .filter(&params)
+        .map_err(ApiError::invalid_input)?;
+    let stream = BroadcastStream::new(state.live.subscribe()).filter_map(move |tick| match tick {
+        Ok(tick) if filter.matches(&tick) => Event::default().json_data(&tick).ok().map(Ok),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Ok(Event::default()
+            .event("lagged")
+            .data(skipped.to_string()))),
+    });
+    let stream =
+        futures::StreamExt::take_until(stream, state.live.shutdown.clone().cancelled_owned());
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,270 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use ticker_core::{
+    ingestion::{
+        FeedState, IngestionConfig, IngestionEvent, IngestionHandle, IngestionSinks,
+        spawn_ingestion_task,
+    },
+    registry::ExchangeRegistry,
+    supervisor::Supervisor,
+    types::{Exchange, Pair},
+};
+use tokio::sync::{mpsc, watch};
+
+use crate::error::ApiError;
+
+/// Ingestion tasks by (exchange, pair). Feeds can be added, paused, resumed
+/// and removed while the server runs.
+pub struct FeedService {
+    pub registry: Arc<ExchangeRegistry>,
+    pub supervisor: Arc<Supervisor>,
+    pub sinks: IngestionSinks,
+    pub config: IngestionConfig,
+    pub events: Option<mpsc::UnboundedSender<IngestionEvent>>,
+    feeds: Mutex<Vec<Feed>>,
+}
+
+pub struct Feed {
+    pub exchange: Exchange,
+    pub pair: Pair,
+    pub state: watch::Receiver<FeedState>,
+    /// `None` while paused.
+    handle: Option<IngestionHandle>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedReport {
+    pub exchange: Exchange,
+    pub symbol: Pair,
+    pub paused: bool,
+    #[serde(flatten)]
+    pub state: FeedState,
+}
+
+/// Body of `POST /admin/subscriptions`.
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionRequest {
+    pub exchange: String,
+    pub pair: String,
+}
+
+impl FeedService {
+    pub fn new(
+        registry: Arc<ExchangeRegistry>,
+        supervisor: Arc<Supervisor>,
+        sinks: IngestionSinks,
+        config: IngestionConfig,
+        events: Option<mpsc::UnboundedSender<IngestionEvent>>,
+    ) -> Self {
+        Self {
+            registry,
+            supervisor,
+            sinks,
+            config,
+            events,
+            feeds: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn get_status(&self) -> Vec<FeedReport> {
+        self.feeds
+            .lock()
+            .unwrap()
+            .iter()
+            .map(Feed::report)
+            .collect()
+    }
+
+    /// Starts ingesting `pair` from `exchange`.
+    pub fn subscribe(&self, exchange: &str, pair: Pair) -> Result<FeedReport, ApiError> {
+        let adapter = self
+            .registry
+            .adapter(exchange)
+            .map_err(ApiError::invalid_input)?;
+        if !adapter.info().supports(&pair) {
+            return Err(ApiError::bad_request(format!(
+                "{} does not list {}",
+                exchange, pair
+            )));
+        }
+
+        let mut feeds = self.feeds.lock().unwrap();
+        if feeds
+            .iter()
+            .any(|feed| feed.exchange == adapter.exchange() && feed.pair == pair)
+        {
+            return Err(ApiError::conflict(format!(
+                "Already subscribed to {} on {}",
+                pair, exchange
+            )));
+        }
+
+        let handle = spawn_ingestion_task(
+            &self.supervisor,
+            self.sinks.clone(),
+            adapter,
+            pair,
+            self.config.clone(),
+            self.events.clone(),
+        );
+        let feed = Feed {
+            exchange: handle.exchange.clone(),
+            pair: handle.pair.clone(),
+            state: handle.state.clone(),
+            handle: Some(handle),
+        };
+        let report = feed.report();
+        feeds.push(feed);
+        Ok(report)
+    }
+
+    /// Stops the task but keeps the feed so it can be resumed.
+    pub fn pause(&self, exchange: &str, pair: &str) -> Result<FeedReport, ApiError> {
+        self.with_feed(exchange, pair, |_, feed| match feed.handle.take() {
+            Some(handle) => {
+                handle.stop();
+                Ok(feed.report())
+            }
+            None => Err(ApiError::conflict("Feed is already paused")),
+        })
+    }
+
+    /// Restarts a paused feed, or one whose task has exited on its own.
+    pub fn resume(&self, exchange: &str, pair: &str) -> Result<FeedReport, ApiError> {
+        self.with_feed(exchange, pair, |service, feed| {
+            if feed
+                .handle
+                .as_ref()
+                .is_some_and(|handle| !handle.task.is_finished())
+            {
+                return Err(ApiError::conflict("Feed is already running"));
+            }
+            let handle = spawn_ingestion_task(
+                &service.supervisor,
+                service.sinks.clone(),
+                service.registry.adapter(feed.exchange.as_str())?,
+                feed.pair.clone(),
+                service.config.clone(),
+                service.events.clone(),
+            );
+            feed.state = handle.state.clone();
+            feed.handle = Some(handle);
+            Ok(feed.report())
+        })
+    }
+
+    /// Stops every feed and waits for them to flush what they have buffered.
+    /// The feeds are left paused.
+    pub async fn shutdown(&self) {
+        let handles: Vec<_> = self
+            .feeds
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter_map(|feed| feed.handle.take())
+            .collect();
+        for handle in &handles {
+            handle.stop();
+        }
+        for handle in handles {
+            handle.shutdown().await;
+        }
+    }
+
+    /// Stops the task and forgets the feed.
+    pub fn unsubscribe(&self, exchange: &str, pair: &str) -> Result<(), ApiError> {
+        let mut feeds = self.feeds.lock().unwrap();
+        let index = find(&feeds, exchange, pair)?;
+        if let Some(handle) = feeds.remove(index).handle {
+            handle.stop();
+        }
+        Ok(())
+    }
+
+    fn with_feed<T>(
+        &self,
+        exchange: &str,
+        pair: &str,
+        f: impl FnOnce(&Self, &mut Feed) -> Result<T, ApiError>,
+    ) -> Result<T, ApiError> {
+        let mut feeds = self.feeds.lock().unwrap();
+        let index = find(&feeds, exchange, pair)?;
+        f(self, &mut feeds[index])
+    }
+}
+
+impl Feed {
+    fn report(&self) -> FeedReport {
+        FeedReport {
+            exchange: self.exchange.clone(),
+            symbol: self.pair.clone(),
+            paused: self.handle.is_none(),
+            state: self.state.borrow().clone(),
+        }
+    }
+}
+
+fn find(feeds: &[Feed], exchange: &str, pair: &str) -> Result<usize, ApiError> {
+    let pair: Pair = pair.parse().map_err(ApiError::invalid_input)?;
+    feeds
+        .iter()
+        .position(|feed| feed.exchange.as_str() == exchange && feed.pair == pair)
+        .ok_or_else(|| ApiError::not_found(format!("No subscription to {} on {}", pair, exchange)))
+}
+
+#[cfg(test)]
+mod tests {
+    use ticker_core::{
+        adapters::ExchangeAdapter,
+        error::TickerError,
+        registry::ExchangeInfo,
+        types::{Event, EventStream, PairFormat},
+    };
+
+    use super::*;
+
+    /// Streams nothing, so a feed stays connected without a network.
+    struct IdleAdapter;
+
+    #[async_trait::async_trait]
+    impl ExchangeAdapter for IdleAdapter {
+        fn info(&self) -> ExchangeInfo {
+            ExchangeInfo::new("Idle", "Idle", PairFormat::Upper)
+        }
+
+        async fn get_event_stream(
+            &self,
+            _pair: &Pair,
+        ) -> Result<EventStream<'_, Event>, TickerError> {
+            Ok(Box::pin(futures::stream::pending()))
+        }
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_keep_one_task_per_feed() {
+        let mut registry = ExchangeRegistry::new();
+        registry.register_adapter(Arc::new(IdleAdapter));
+        let supervisor = Arc::new(Supervisor::default());
+        let (ticks, _rx) = mpsc::channel(16);
+        let service = FeedService::new(
+            Arc::new(registry),
+            supervisor.clone(),
+            IngestionSinks::new(ticks),
+            IngestionConfig::default(),
+            None,
+        );
+
+        service.subscribe("Idle", Pair::new("SOL", "USDT")).unwrap();
+        assert_eq!(supervisor.states().len(), 1);
+        for _ in 0..3 {
+            service.pause("Idle", "SOL-USDT").unwrap();
+            assert!(supervisor.states().is_empty());
+            service.resume("Idle", "SOL-USDT").unwrap();
+            assert_eq!(supervisor.states().len(), 1);
+        }
+
+        service.unsubscribe("Idle", "SOL-USDT").unwrap();
+        assert!(supervisor.states().is_empty());
+    }
+}
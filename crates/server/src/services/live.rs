@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use ticker_core::{
+    error::TickerError,
+    registry::ExchangeRegistry,
+    types::{Exchange, Pair, PriceTick},
+};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use super::price::split_list;
+
+pub struct LiveService {
+    pub ticks: broadcast::Sender<PriceTick>,
+    pub registry: Arc<ExchangeRegistry>,
+    /// Ends every open stream so the HTTP server can shut down.
+    pub shutdown: CancellationToken,
+}
+
+/// Query string of `/ws` and `/sse`, comma-separated like `/ticks`.
+#[derive(Debug, Default, Deserialize)]
+pub struct LiveParams {
+    pub exchange: Option<String>,
+    pub pair: Option<String>,
+}
+
+/// Which ticks a live subscriber receives; empty sets match everything.
+#[derive(Debug, Clone, Default)]
+pub struct LiveFilter {
+    pub exchanges: Vec<Exchange>,
+    pub pairs: Vec<Pair>,
+}
+
+impl LiveService {
+    pub fn subscribe(&self) -> broadcast::Receiver<PriceTick> {
+        self.ticks.subscribe()
+    }
+
+    pub fn filter(&self, params: &LiveParams) -> Result<LiveFilter, TickerError> {
+        Ok(LiveFilter {
+            exchanges: split_list(&params.exchange)
+                .map(|exchange| self.registry.resolve(exchange))
+                .collect::<Result<_, _>>()?,
+            pairs: split_list(&params.pair)
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl LiveFilter {
+    pub fn matches(&self, tick: &PriceTick) -> bool {
+        (self.exchanges.is_empty() || self.exchanges.contains(&tick.exchange))
+            && (self.pairs.is_empty() || self.pairs.contains(&tick.symbol))
+    }
+}
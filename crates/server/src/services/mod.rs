@@ -1,3 +1,7 @@
+mod feed;
+mod live;
 mod price;
 
+pub use feed::*;
+pub use live::*;
 pub use price::*;
@@ -1,13 +1,253 @@
-use ticker_core::{error::TickerError, storage::select_price_ticks_after, types::PriceTick};
+use std::sync::Arc;
 
-use crate::config::DURATION_SEC;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use ticker_core::{
+    candles::TickCandles,
+    error::TickerError,
+    registry::{ExchangeInfo, ExchangeRegistry},
+    retention::RetentionPolicy,
+    storage::{CandleQuery, SortOrder, TickPage, TickQuery, TickStore},
+    types::{Candle, Exchange, Pair, Resolution},
+};
+
+use crate::config::{
+    CANDLES_DEFAULT_COUNT, CANDLES_DEFAULT_INTERVAL, CANDLES_MAX_COUNT, CANDLES_TICKS_PAGE_SIZE,
+    TICKS_DEFAULT_LIMIT, TICKS_MAX_LIMIT,
+};
+
+/// Tick series and `[from, to)` range read for part of a `/candles` query.
+type TickSource = (
+    Option<Resolution>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+);
 
 pub struct PriceService {
-    pub db: sqlx::SqlitePool,
+    pub store: Arc<dyn TickStore>,
+    pub registry: Arc<ExchangeRegistry>,
+    /// Tiers the retention task keeps: the raw `price_ticks` series first,
+    /// then the rollups it writes.
+    pub retention: RetentionPolicy,
+    /// Resolutions the ingestion tasks store in the `candles` table.
+    pub candle_resolutions: Vec<Resolution>,
+}
+
+/// Query string of `/ticks`. `exchange` and `pair` take comma-separated
+/// lists; `from` and `to` take RFC 3339 or epoch milliseconds.
+#[derive(Debug, Default, Deserialize)]
+pub struct TicksParams {
+    pub exchange: Option<String>,
+    pub pair: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+    pub resolution: Option<String>,
+    pub cursor: Option<String>,
+    pub order: Option<String>,
+}
+
+/// Query string of `/candles`, parsed like [`TicksParams`]. `interval`
+/// defaults to one minute.
+#[derive(Debug, Default, Deserialize)]
+pub struct CandlesParams {
+    pub exchange: Option<String>,
+    pub pair: Option<String>,
+    pub interval: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
 }
 
 impl PriceService {
-    pub async fn get_ticks(&self) -> Result<Vec<PriceTick>, TickerError> {
-        select_price_ticks_after(&self.db, DURATION_SEC).await
+    pub async fn get_ticks(&self, query: &TickQuery) -> Result<TickPage, TickerError> {
+        self.store.query_ticks(query).await
+    }
+
+    pub fn tick_query(&self, params: &TicksParams) -> Result<TickQuery, TickerError> {
+        let limit = params.limit.unwrap_or(TICKS_DEFAULT_LIMIT);
+        if limit == 0 || limit > TICKS_MAX_LIMIT {
+            return Err(TickerError::InvalidArgument(format!(
+                "limit must be between 1 and {}",
+                TICKS_MAX_LIMIT
+            )));
+        }
+
+        let mut query = TickQuery::new()
+            .with_exchanges(split_list(&params.exchange).map(Exchange::new))
+            .with_pairs(
+                split_list(&params.pair)
+                    .map(str::parse::<Pair>)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+            .with_limit(limit);
+
+        if let Some(from) = &params.from {
+            query = query.with_from(parse_timestamp("from", from)?);
+        }
+        if let Some(to) = &params.to {
+            query = query.with_to(parse_timestamp("to", to)?);
+        }
+        if let (Some(from), Some(to)) = (query.from, query.to)
+            && from >= to
+        {
+            return Err(TickerError::InvalidArgument(
+                "from must be before to".to_string(),
+            ));
+        }
+        if let Some(resolution) = &params.resolution {
+            let resolution = resolution.parse::<Resolution>()?;
+            query =
+                query.with_resolution((resolution != self.raw_resolution()).then_some(resolution));
+        }
+        if let Some(order) = &params.order {
+            query = query.with_order(order.parse()?);
+        }
+        if let Some(cursor) = &params.cursor {
+            query = query.with_cursor(cursor.parse()?);
+        }
+
+        Ok(query)
+    }
+
+    /// Reads the `candles` table when the ingestion tasks store bars of this
+    /// interval, otherwise builds them from stored ticks. Each part of the
+    /// range is read from one tier only, the finest still holding it whose
+    /// resolution divides the interval, a page at a time.
+    pub async fn get_candles(&self, query: &CandleQuery) -> Result<Vec<Candle>, TickerError> {
+        if self.candle_resolutions.contains(&query.resolution) {
+            return self.store.query_candles(query).await;
+        }
+
+        let mut candles = TickCandles::new(query.resolution);
+        for (source, from, to) in self.tick_sources(query).into_iter().rev() {
+            let mut tick_query = TickQuery::new()
+                .with_resolution(source)
+                .with_exchanges(query.exchanges.iter().cloned())
+                .with_pairs(query.pairs.iter().cloned())
+                .with_order(SortOrder::Asc)
+                .with_limit(CANDLES_TICKS_PAGE_SIZE);
+            if let Some(from) = from {
+                tick_query = tick_query.with_from(from);
+            }
+            if let Some(to) = to {
+                tick_query = tick_query.with_to(to);
+            }
+            loop {
+                let page = self.store.query_ticks(&tick_query).await?;
+                for tick in &page.ticks {
+                    candles.push(tick);
+                }
+                let Some(cursor) = page.next_cursor else {
+                    break;
+                };
+                tick_query = tick_query.with_cursor(cursor);
+            }
+        }
+
+        Ok(candles.finish())
     }
+
+    /// Splits the range of `query` by retention tier, newest part first, at
+    /// the boundaries the retention task last rolled each tier up to.
+    fn tick_sources(&self, query: &CandleQuery) -> Vec<TickSource> {
+        let now = Utc::now();
+        let interval = query.resolution.as_millis();
+        let mut sources = Vec::new();
+        let mut to = query.to;
+        for (i, tier) in self.retention.tiers().iter().enumerate() {
+            if interval % tier.resolution.as_millis() != 0 {
+                continue;
+            }
+            let source = (i > 0).then_some(tier.resolution);
+            let from = match (self.retention.tier_boundary(i, now), query.from) {
+                (Some(cutoff), Some(from)) => Some(cutoff.max(from)),
+                (cutoff, from) => cutoff.or(from),
+            };
+            if let (Some(from), Some(to)) = (from, to)
+                && from >= to
+            {
+                continue;
+            }
+            sources.push((source, from, to));
+            if from.is_none() || from == query.from {
+                break;
+            }
+            to = from;
+        }
+        sources
+    }
+
+    pub fn candle_query(&self, params: &CandlesParams) -> Result<CandleQuery, TickerError> {
+        let resolution = params
+            .interval
+            .as_deref()
+            .unwrap_or(CANDLES_DEFAULT_INTERVAL)
+            .parse::<Resolution>()?;
+        if resolution.as_millis() % self.raw_resolution().as_millis() != 0 {
+            return Err(TickerError::InvalidArgument(format!(
+                "interval must be a multiple of {}",
+                self.raw_resolution()
+            )));
+        }
+
+        let width = chrono::Duration::milliseconds(resolution.as_millis());
+        let to = match &params.to {
+            Some(to) => parse_timestamp("to", to)?,
+            None => Utc::now(),
+        };
+        let from = match &params.from {
+            Some(from) => parse_timestamp("from", from)?,
+            None => to - width * CANDLES_DEFAULT_COUNT as i32,
+        };
+        let from = resolution.bucket_start(from);
+        if from >= to {
+            return Err(TickerError::InvalidArgument(
+                "from must be before to".to_string(),
+            ));
+        }
+        let count = ((to - from).num_milliseconds() as u64).div_ceil(resolution.as_millis() as u64);
+        if count > CANDLES_MAX_COUNT as u64 {
+            return Err(TickerError::InvalidArgument(format!(
+                "Range spans more than {} candles",
+                CANDLES_MAX_COUNT
+            )));
+        }
+
+        Ok(CandleQuery::new(resolution)
+            .with_exchanges(split_list(&params.exchange).map(Exchange::new))
+            .with_pairs(
+                split_list(&params.pair)
+                    .map(str::parse::<Pair>)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+            .with_from(from)
+            .with_to(to))
+    }
+
+    fn raw_resolution(&self) -> Resolution {
+        self.retention.tiers()[0].resolution
+    }
+
+    pub fn get_exchanges(&self) -> Vec<ExchangeInfo> {
+        let mut exchanges: Vec<_> = self.registry.exchanges().cloned().collect();
+        exchanges.sort_by(|a, b| a.exchange.as_str().cmp(b.exchange.as_str()));
+        exchanges
+    }
+}
+
+pub(crate) fn split_list(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn parse_timestamp(name: &str, value: &str) -> Result<DateTime<Utc>, TickerError> {
+    let parsed = match value.parse::<i64>() {
+        Ok(millis) => DateTime::from_timestamp_millis(millis),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|ts| ts.with_timezone(&Utc)),
+    };
+    parsed.ok_or_else(|| TickerError::InvalidArgument(format!("Invalid {}: {}", name, value)))
 }
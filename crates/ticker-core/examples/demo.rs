@@ -1,33 +1,49 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use sqlx::SqlitePool;
-use ticker_core::{adapters::BinanceAdapter, types::PriceTick};
+use ticker_core::{
+    adapters::BinanceAdapter,
+    ingestion::{IngestionConfig, IngestionSinks, spawn_ingestion_task},
+    storage::BatchConfig,
+    supervisor::{Supervisor, TaskStatus},
+    types::PriceTick,
+};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
-    let db = SqlitePool::connect("sqlite:./db/prices.db")
+    let store = ticker_core::storage::connect("sqlite:./db/prices.db")
         .await
         .expect("Failed to connect to database");
 
-    let (tx, rx) = mpsc::channel::<PriceTick>(100);
-    let db_fut = ticker_core::storage::run_db_task(db, rx);
-    let ingestion_fut = ticker_core::ingestion::run_ingestion_task(
-        tx,
-        BinanceAdapter,
-        ticker_core::types::Pair::SOLUSDT,
-        100_000,
-        Duration::from_millis(100),
+    let (tx, mut rx) = mpsc::channel::<PriceTick>(100);
+    let db_cancel = CancellationToken::new();
+    let db = tokio::spawn({
+        let cancel = db_cancel.clone();
+        async move {
+            ticker_core::storage::run_db_task(store, &mut rx, BatchConfig::default(), cancel).await
+        }
+    });
+    let supervisor = Supervisor::default();
+    let ingestion = spawn_ingestion_task(
+        &supervisor,
+        IngestionSinks::new(tx),
+        Arc::new(BinanceAdapter),
+        ticker_core::types::Pair::new("SOL", "USDT"),
+        IngestionConfig {
+            buffer_size: 100_000,
+            tick: Duration::from_millis(100),
+            ..Default::default()
+        },
+        None,
     );
+    let mut ingestion_state = ingestion.task.state.clone();
 
     tokio::select! {
-        res = db_fut => {
-            if let Err(e) = res {
-                eprintln!("Database task error: {}", e);
-            }
-        },
-        res = ingestion_fut => {
-            if let Err(e) = res {
+        Ok(state) = ingestion_state.wait_for(|state| {
+            matches!(state.status, TaskStatus::Finished | TaskStatus::Failed)
+        }) => {
+            if let Some(e) = &state.last_error {
                 eprintln!("Ingestion task error: {}", e);
             }
         },
@@ -36,5 +52,12 @@ async fn main() {
         },
     }
 
+    // Flush the open bucket, then let the database task drain the channel.
+    ingestion.shutdown().await;
+    db_cancel.cancel();
+    if let Ok(Err(e)) = db.await {
+        eprintln!("Database task error: {}", e);
+    }
+
     println!("Demo finished.");
 }
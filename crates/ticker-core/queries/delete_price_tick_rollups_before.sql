@@ -0,0 +1,3 @@
+DELETE FROM price_tick_rollups
+WHERE resolution_ms = $1
+  AND ts < $2;
\ No newline at end of file
@@ -0,0 +1,2 @@
+DELETE FROM price_ticks
+WHERE ts < $1;
\ No newline at end of file
@@ -1,2 +0,0 @@
-INSERT INTO price_ticks (exchange, symbol, price, sz, ts)
-VALUES ($1, $2, $3, $4, $5);
\ No newline at end of file
@@ -1,8 +0,0 @@
-CREATE TABLE IF NOT EXISTS price_ticks (
-    id INTEGER PRIMARY KEY AUTOINCREMENT,
-    exchange TEXT NOT NULL,
-    symbol TEXT NOT NULL,
-    price REAL NOT NULL,
-    sz REAL NOT NULL,
-    ts TIMESTAMP NOT NULL
-);
\ No newline at end of file
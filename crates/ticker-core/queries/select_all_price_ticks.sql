@@ -1,4 +0,0 @@
-SELECT *
-FROM price_ticks
-ORDER BY ts DESC
-LIMIT $1;
\ No newline at end of file
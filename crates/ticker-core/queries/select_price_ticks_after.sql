@@ -1,4 +0,0 @@
-SELECT *
-FROM price_ticks
-WHERE ts >= $1
-ORDER BY ts DESC;
\ No newline at end of file
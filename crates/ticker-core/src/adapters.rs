@@ -8,11 +8,17 @@ pub use coinbase::*;
 
 use crate::{
     error::TickerError,
+    registry::ExchangeInfo,
     types::{Event, EventStream, Exchange, Pair},
 };
 
 #[async_trait::async_trait]
-pub trait ExchangeAdapter {
-    fn kind() -> Exchange;
+pub trait ExchangeAdapter: Send + Sync {
+    fn info(&self) -> ExchangeInfo;
+
+    fn exchange(&self) -> Exchange {
+        self.info().exchange
+    }
+
     async fn get_event_stream(&self, pair: &Pair) -> Result<EventStream<'_, Event>, TickerError>;
 }
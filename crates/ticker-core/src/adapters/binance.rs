@@ -4,7 +4,8 @@ use tokio_stream::StreamExt as _;
 use crate::{
     adapters::ExchangeAdapter,
     error::TickerError,
-    types::{Event, EventStream, Exchange, Pair, PairFormat, RawPriceTick},
+    registry::ExchangeInfo,
+    types::{Event, EventStream, Pair, PairFormat, RawPriceTick, Side},
 };
 
 #[derive(Clone)]
@@ -12,13 +13,13 @@ pub struct BinanceAdapter;
 
 #[async_trait::async_trait]
 impl ExchangeAdapter for BinanceAdapter {
-    fn kind() -> Exchange {
-        Exchange::Binance
+    fn info(&self) -> ExchangeInfo {
+        ExchangeInfo::new("Binance", "Binance", PairFormat::Lower)
     }
 
     async fn get_event_stream(&self, pair: &Pair) -> Result<EventStream<'_, Event>, TickerError> {
         let (stream, _) = exstreamer::StreamBuilder::binance()
-            .with_trade(pair.format(PairFormat::Lower))
+            .with_trade(self.info().symbol(pair))
             .connect()
             .await?;
 
@@ -37,6 +38,16 @@ impl TryFrom<BinanceTrade> for RawPriceTick {
             size: trade.quantity.parse()?,
             timestamp: chrono::DateTime::from_timestamp_millis(trade.trade_time as i64)
                 .ok_or_else(|| TickerError::RawEventParseError("Invalid timestamp".to_string()))?,
+            received_at: chrono::Utc::now(),
+            // The buyer being the maker means the seller crossed the spread.
+            side: Some(if trade.is_buyer_maker {
+                Side::Sell
+            } else {
+                Side::Buy
+            }),
+            trade_id: Some(trade.trade_id.to_string()),
+            // Binance trade ids are sequential per symbol.
+            sequence: Some(trade.trade_id),
         })
     }
 }
@@ -4,7 +4,8 @@ use futures::{StreamExt, stream};
 use crate::{
     adapters::ExchangeAdapter,
     error::TickerError,
-    types::{Event, EventStream, Exchange, Pair, PairFormat, RawPriceTick},
+    registry::ExchangeInfo,
+    types::{Event, EventStream, Pair, PairFormat, RawPriceTick},
 };
 
 #[derive(Clone)]
@@ -12,13 +13,13 @@ pub struct BybitAdapter;
 
 #[async_trait::async_trait]
 impl ExchangeAdapter for BybitAdapter {
-    fn kind() -> Exchange {
-        Exchange::Bybit
+    fn info(&self) -> ExchangeInfo {
+        ExchangeInfo::new("Bybit", "Bybit", PairFormat::Lower)
     }
 
     async fn get_event_stream(&self, pair: &Pair) -> Result<EventStream<'_, Event>, TickerError> {
         let (stream, _) = exstreamer::StreamBuilder::bybit()
-            .with_trade(pair.format(PairFormat::Lower))
+            .with_trade(self.info().symbol(pair))
             .connect()
             .await?;
 
@@ -37,6 +38,10 @@ impl TryFrom<BybitTradeData> for RawPriceTick {
             size: trade.size.parse()?,
             timestamp: chrono::DateTime::from_timestamp_millis(trade.timestamp as i64)
                 .ok_or_else(|| TickerError::RawEventParseError("Invalid timestamp".to_string()))?,
+            received_at: chrono::Utc::now(),
+            side: Some(trade.side.parse()?),
+            trade_id: Some(trade.trade_id),
+            sequence: None,
         })
     }
 }
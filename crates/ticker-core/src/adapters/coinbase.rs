@@ -4,7 +4,8 @@ use tokio_stream::StreamExt as _;
 use crate::{
     adapters::ExchangeAdapter,
     error::TickerError,
-    types::{Event, EventStream, Exchange, Pair, PairFormat, RawPriceTick},
+    registry::ExchangeInfo,
+    types::{Event, EventStream, Pair, PairFormat, RawPriceTick},
 };
 
 #[derive(Clone)]
@@ -12,13 +13,13 @@ pub struct CoinbaseAdapter;
 
 #[async_trait::async_trait]
 impl ExchangeAdapter for CoinbaseAdapter {
-    fn kind() -> Exchange {
-        Exchange::Coinbase
+    fn info(&self) -> ExchangeInfo {
+        ExchangeInfo::new("Coinbase", "Coinbase", PairFormat::UpperWithDash)
     }
 
     async fn get_event_stream(&self, pair: &Pair) -> Result<EventStream<'_, Event>, TickerError> {
         let (stream, _) = exstreamer::StreamBuilder::coinbase()
-            .with_trade(pair.format(PairFormat::UpperWithDash))
+            .with_trade(self.info().symbol(pair))
             .connect()
             .await?;
 
@@ -40,6 +41,11 @@ impl TryFrom<CoinbaseTicker> for RawPriceTick {
             price: tick.price.parse()?,
             size: tick.last_size.parse()?,
             timestamp,
+            received_at: chrono::Utc::now(),
+            // Coinbase reports the taker side on the ticker channel.
+            side: Some(tick.side.parse()?),
+            trade_id: Some(tick.trade_id.to_string()),
+            sequence: Some(tick.sequence),
         })
     }
 }
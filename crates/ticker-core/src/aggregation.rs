@@ -0,0 +1,177 @@
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator as _};
+use serde::Serialize;
+
+use crate::{error::TickerError, types::RawPriceTick};
+
+/// Reduces the valid trades of one bucket to a reference price. Trades arrive
+/// in the order they were received; `end` is the close of the bucket.
+pub trait Aggregator: std::fmt::Debug + Send + Sync {
+    fn aggregate(&self, trades: &[RawPriceTick], end: DateTime<Utc>) -> Option<f64>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum AggregationMethod {
+    Vwap,
+    LastTrade,
+    Twap,
+    Median,
+    TrimmedMean(f64),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vwap;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LastTrade;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Twap;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Median;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrimmedMean {
+    /// Fraction of trades dropped from each tail, clamped to `[0, 0.5)`.
+    pub trim: f64,
+}
+
+impl AggregationMethod {
+    pub fn build(self) -> Arc<dyn Aggregator> {
+        match self {
+            AggregationMethod::Vwap => Arc::new(Vwap),
+            AggregationMethod::LastTrade => Arc::new(LastTrade),
+            AggregationMethod::Twap => Arc::new(Twap),
+            AggregationMethod::Median => Arc::new(Median),
+            AggregationMethod::TrimmedMean(trim) => Arc::new(TrimmedMean { trim }),
+        }
+    }
+}
+
+impl FromStr for AggregationMethod {
+    type Err = TickerError;
+
+    /// Parses `vwap`, `last`, `twap`, `median` or `trimmed_mean:<fraction>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TickerError::InvalidArgument(format!("Unknown aggregation: {}", s));
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+
+        match (name.trim().to_lowercase().as_str(), arg) {
+            ("vwap", None) => Ok(AggregationMethod::Vwap),
+            ("last" | "last_trade", None) => Ok(AggregationMethod::LastTrade),
+            ("twap", None) => Ok(AggregationMethod::Twap),
+            ("median", None) => Ok(AggregationMethod::Median),
+            ("trimmed_mean", Some(trim)) => trim
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|trim| (0.0..0.5).contains(trim))
+                .map(AggregationMethod::TrimmedMean)
+                .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl std::fmt::Display for AggregationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregationMethod::Vwap => write!(f, "vwap"),
+            AggregationMethod::LastTrade => write!(f, "last"),
+            AggregationMethod::Twap => write!(f, "twap"),
+            AggregationMethod::Median => write!(f, "median"),
+            AggregationMethod::TrimmedMean(trim) => write!(f, "trimmed_mean:{}", trim),
+        }
+    }
+}
+
+impl Aggregator for Vwap {
+    fn aggregate(&self, trades: &[RawPriceTick], _end: DateTime<Utc>) -> Option<f64> {
+        let (weighted_sum_price, total_size) = trades
+            .par_iter()
+            .map(|tick| (tick.price * tick.size, tick.size))
+            .reduce(
+                || (0.0, 0.0),
+                |(sum_vw, sum_w), (vw, w)| (sum_vw + vw, sum_w + w),
+            );
+
+        (total_size > 0.0).then(|| weighted_sum_price / total_size)
+    }
+}
+
+impl Aggregator for LastTrade {
+    fn aggregate(&self, trades: &[RawPriceTick], _end: DateTime<Utc>) -> Option<f64> {
+        trades
+            .iter()
+            .max_by_key(|tick| tick.timestamp)
+            .map(|tick| tick.price)
+    }
+}
+
+impl Aggregator for Twap {
+    fn aggregate(&self, trades: &[RawPriceTick], end: DateTime<Utc>) -> Option<f64> {
+        let mut sorted: Vec<_> = trades.iter().collect();
+        sorted.sort_by_key(|tick| tick.timestamp);
+
+        let mut weighted_sum_price = 0.0;
+        let mut total_weight = 0.0;
+        for (i, tick) in sorted.iter().enumerate() {
+            let until = sorted.get(i + 1).map_or(end, |next| next.timestamp);
+            let weight = (until - tick.timestamp)
+                .num_microseconds()
+                .unwrap_or(0)
+                .max(0) as f64;
+            weighted_sum_price += tick.price * weight;
+            total_weight += weight;
+        }
+
+        if total_weight > 0.0 {
+            Some(weighted_sum_price / total_weight)
+        } else {
+            mean(sorted.iter().map(|tick| tick.price))
+        }
+    }
+}
+
+impl Aggregator for Median {
+    fn aggregate(&self, trades: &[RawPriceTick], _end: DateTime<Utc>) -> Option<f64> {
+        median(trades.iter().map(|tick| tick.price))
+    }
+}
+
+impl Aggregator for TrimmedMean {
+    fn aggregate(&self, trades: &[RawPriceTick], _end: DateTime<Utc>) -> Option<f64> {
+        let prices = sorted_prices(trades);
+        let cut = (prices.len() as f64 * self.trim.clamp(0.0, 0.49)).floor() as usize;
+        mean(prices[cut..prices.len() - cut].iter().copied())
+    }
+}
+
+fn sorted_prices(trades: &[RawPriceTick]) -> Vec<f64> {
+    let mut prices: Vec<_> = trades.iter().map(|tick| tick.price).collect();
+    prices.sort_by(f64::total_cmp);
+    prices
+}
+
+/// Middle price, or the mean of the two middle prices for an even count.
+pub(crate) fn median(prices: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let mut prices: Vec<_> = prices.into_iter().collect();
+    prices.sort_by(f64::total_cmp);
+    let mid = prices.len() / 2;
+    match prices.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(prices[mid]),
+        _ => Some((prices[mid - 1] + prices[mid]) / 2.0),
+    }
+}
+
+fn mean(prices: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = prices.fold((0.0, 0usize), |(sum, count), p| (sum + p, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::types::{Candle, Exchange, Pair, PriceTick, RawPriceTick, Resolution};
+
+/// Builds OHLCV bars for one (exchange, pair, resolution) from individual
+/// trades, bucketed by the exchange timestamp of each trade.
+#[derive(Debug)]
+pub struct CandleBuilder {
+    exchange: Exchange,
+    pair: Pair,
+    resolution: Resolution,
+    current: Option<Candle>,
+    notional: f64,
+    closed_until: Option<DateTime<Utc>>,
+}
+
+impl CandleBuilder {
+    pub fn new(exchange: Exchange, pair: Pair, resolution: Resolution) -> Self {
+        Self {
+            exchange,
+            pair,
+            resolution,
+            current: None,
+            notional: 0.0,
+            closed_until: None,
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Adds a trade to the open bar. Returns the previous bar when the trade
+    /// starts a new bucket. Trades older than the open bar, or than the last
+    /// bar emitted, are ignored since their bar has already been emitted, as
+    /// are trades without a positive price and size like in aggregation.
+    pub fn push(&mut self, trade: &RawPriceTick) -> Option<Candle> {
+        if !(trade.price > 0.0 && trade.size > 0.0) {
+            return None;
+        }
+        let start = self.resolution.bucket_start(trade.timestamp);
+        if self.closed_until.is_some_and(|closed| start < closed) {
+            return None;
+        }
+        let closed = match &self.current {
+            Some(candle) if start < candle.timestamp => return None,
+            Some(candle) if start > candle.timestamp => self.take(),
+            _ => None,
+        };
+
+        match &mut self.current {
+            Some(candle) => {
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.close = trade.price;
+                candle.volume += trade.size;
+                candle.trade_count += 1;
+                self.notional += trade.price * trade.size;
+                candle.vwap = self.notional / candle.volume;
+            }
+            None => {
+                self.notional = trade.price * trade.size;
+                self.current = Some(Candle {
+                    exchange: self.exchange.clone(),
+                    symbol: self.pair.clone(),
+                    resolution: self.resolution,
+                    timestamp: start,
+                    open: trade.price,
+                    high: trade.price,
+                    low: trade.price,
+                    close: trade.price,
+                    volume: trade.size,
+                    trade_count: 1,
+                    vwap: trade.price,
+                });
+            }
+        }
+
+        closed
+    }
+
+    /// Adds an aggregated tick as if it were one trade at its price and size.
+    pub fn push_tick(&mut self, tick: &PriceTick) -> Option<Candle> {
+        self.push(&RawPriceTick {
+            price: tick.price,
+            size: tick.size,
+            timestamp: tick.timestamp,
+            received_at: tick.timestamp,
+            side: None,
+            trade_id: None,
+            sequence: None,
+        })
+    }
+
+    /// Emits the open bar if its bucket ended at or before `watermark`.
+    pub fn close_expired(&mut self, watermark: DateTime<Utc>) -> Option<Candle> {
+        let end = self.resolution.bucket_end(self.current.as_ref()?.timestamp);
+        if end <= watermark { self.take() } else { None }
+    }
+
+    /// Emits the open bar regardless of whether its bucket has ended.
+    pub fn take(&mut self) -> Option<Candle> {
+        self.notional = 0.0;
+        let candle = self.current.take()?;
+        self.closed_until = Some(self.resolution.bucket_end(candle.timestamp));
+        Some(candle)
+    }
+}
+
+/// Builds bars for every (exchange, pair) in a series of stored ticks fed
+/// oldest first, see [`CandleBuilder::push_tick`].
+#[derive(Debug)]
+pub struct TickCandles {
+    resolution: Resolution,
+    builders: HashMap<(Exchange, Pair), CandleBuilder>,
+    candles: Vec<Candle>,
+}
+
+impl TickCandles {
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            builders: HashMap::new(),
+            candles: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, tick: &PriceTick) {
+        let builder = self
+            .builders
+            .entry((tick.exchange.clone(), tick.symbol.clone()))
+            .or_insert_with(|| {
+                CandleBuilder::new(tick.exchange.clone(), tick.symbol.clone(), self.resolution)
+            });
+        self.candles.extend(builder.push_tick(tick));
+    }
+
+    /// Closes the open bars and returns every bar ordered by timestamp, then
+    /// exchange and pair.
+    pub fn finish(mut self) -> Vec<Candle> {
+        self.candles
+            .extend(self.builders.values_mut().filter_map(CandleBuilder::take));
+        self.candles.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.exchange.as_str().cmp(b.exchange.as_str()))
+                .then_with(|| a.symbol.to_string().cmp(&b.symbol.to_string()))
+        });
+        self.candles
+    }
+}
+
+/// Builds bars from stored ticks in any order, see [`TickCandles`].
+pub fn candles_from_ticks(
+    ticks: impl IntoIterator<Item = PriceTick>,
+    resolution: Resolution,
+) -> Vec<Candle> {
+    let mut ticks: Vec<_> = ticks.into_iter().collect();
+    ticks.sort_by_key(|tick| tick.timestamp);
+
+    let mut candles = TickCandles::new(resolution);
+    for tick in &ticks {
+        candles.push(tick);
+    }
+    candles.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::trade;
+
+    #[test]
+    fn skips_invalid_and_already_emitted_trades() {
+        let mut builder = CandleBuilder::new(
+            Exchange::new("Binance"),
+            Pair::new("SOL", "USDT"),
+            Resolution::from_millis(1_000).unwrap(),
+        );
+        for trade in [
+            trade(100, 10.0, 1.0),
+            trade(200, 0.0, 1.0),
+            trade(300, 12.0, 0.0),
+            trade(400, f64::NAN, 1.0),
+            trade(500, 14.0, 3.0),
+        ] {
+            assert!(builder.push(&trade).is_none());
+        }
+
+        let candle = builder.push(&trade(1_100, 20.0, 1.0)).unwrap();
+        assert_eq!((candle.open, candle.high, candle.low), (10.0, 14.0, 10.0));
+        assert_eq!((candle.volume, candle.trade_count), (4.0, 2));
+        assert_eq!(candle.vwap, 13.0);
+
+        assert!(builder.push(&trade(900, 30.0, 1.0)).is_none());
+        let candle = builder.take().unwrap();
+        assert_eq!((candle.open, candle.volume), (20.0, 1.0));
+    }
+}
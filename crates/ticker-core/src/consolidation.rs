@@ -0,0 +1,359 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use tokio::{
+    sync::broadcast::{
+        self,
+        error::{RecvError, TryRecvError},
+    },
+    time::MissedTickBehavior,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    aggregation::median,
+    candles::CandleBuilder,
+    error::TickerError,
+    ingestion::{IngestionSinks, Outbox},
+    registry::ExchangeInfo,
+    types::{Exchange, Pair, PairFormat, PriceTick, Resolution},
+};
+
+/// How the prices of the venues quoting a pair are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum ConsolidationMethod {
+    /// Mean weighted by the size of each venue's latest tick.
+    #[default]
+    VolumeWeighted,
+    Median,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsolidationConfig {
+    /// Synthetic exchange the composite ticks are stored and served under.
+    pub exchange: Exchange,
+    pub method: ConsolidationMethod,
+    /// Venues without a tick for this long are left out.
+    pub stale_after: Duration,
+    /// Venues further than this fraction from the median are left out,
+    /// e.g. `0.02` for 2%.
+    pub max_deviation: f64,
+    /// Fewest venues left after filtering for a composite to be emitted.
+    pub min_venues: usize,
+    pub tick: Duration,
+    /// OHLCV bar widths built from the composite ticks, none by default.
+    pub candle_resolutions: Vec<Resolution>,
+    /// Output held per channel while its receiver is full, oldest dropped
+    /// first.
+    pub buffer_size: usize,
+}
+
+/// Latest tick of one venue, plus the volume it traded since the last
+/// composite was emitted.
+#[derive(Debug)]
+struct Venue {
+    tick: PriceTick,
+    received: Instant,
+    pending: Option<(f64, f64, f64)>,
+}
+
+/// Combines per-exchange ticks into one composite tick per pair.
+#[derive(Debug)]
+pub struct Consolidator {
+    config: ConsolidationConfig,
+    pairs: HashMap<Pair, HashMap<Exchange, Venue>>,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        Self {
+            exchange: Exchange::new("Consolidated"),
+            method: ConsolidationMethod::VolumeWeighted,
+            stale_after: Duration::from_secs(5),
+            max_deviation: 0.02,
+            min_venues: 1,
+            tick: Duration::from_millis(100),
+            candle_resolutions: Vec::new(),
+            buffer_size: 100_000,
+        }
+    }
+}
+
+impl ConsolidationConfig {
+    /// Registry entry for the synthetic exchange; it has no adapter.
+    pub fn info(&self) -> ExchangeInfo {
+        ExchangeInfo::new(self.exchange.as_str(), "Consolidated", PairFormat::Upper)
+    }
+}
+
+impl Consolidator {
+    pub fn new(config: ConsolidationConfig) -> Self {
+        Self {
+            config,
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Records a venue tick. Ticks of the synthetic exchange itself are
+    /// ignored so the composite can share a channel with its inputs.
+    pub fn push(&mut self, tick: PriceTick) {
+        if tick.exchange == self.config.exchange {
+            return;
+        }
+        let venues = self.pairs.entry(tick.symbol.clone()).or_default();
+        let (size, buy_size, sell_size) = venues
+            .get(&tick.exchange)
+            .and_then(|venue| venue.pending)
+            .unwrap_or_default();
+        let pending = Some((
+            size + tick.size,
+            buy_size + tick.buy_size,
+            sell_size + tick.sell_size,
+        ));
+        venues.insert(
+            tick.exchange.clone(),
+            Venue {
+                tick,
+                received: Instant::now(),
+                pending,
+            },
+        );
+    }
+
+    /// Emits a composite for every pair that received ticks since the last
+    /// call, using the venues that are fresh and within the deviation band.
+    pub fn emit(&mut self, now: Instant) -> Vec<PriceTick> {
+        let mut ticks = Vec::new();
+        for (pair, venues) in &mut self.pairs {
+            if venues.values().all(|venue| venue.pending.is_none()) {
+                continue;
+            }
+            if let Some(tick) = consolidate(&self.config, pair, venues, now) {
+                ticks.push(tick);
+            }
+            for venue in venues.values_mut() {
+                venue.pending = None;
+            }
+        }
+        ticks
+    }
+}
+
+fn consolidate(
+    config: &ConsolidationConfig,
+    pair: &Pair,
+    venues: &HashMap<Exchange, Venue>,
+    now: Instant,
+) -> Option<PriceTick> {
+    let fresh: Vec<_> = venues
+        .values()
+        .filter(|venue| now.duration_since(venue.received) <= config.stale_after)
+        .collect();
+    let reference = median(fresh.iter().map(|venue| venue.tick.price))?;
+    let kept: Vec<_> = fresh
+        .into_iter()
+        .filter(|venue| {
+            (venue.tick.price - reference).abs() <= reference.abs() * config.max_deviation
+        })
+        .collect();
+    if kept.is_empty() || kept.len() < config.min_venues {
+        return None;
+    }
+
+    let price = match config.method {
+        ConsolidationMethod::Median => median(kept.iter().map(|venue| venue.tick.price))?,
+        ConsolidationMethod::VolumeWeighted => {
+            let weight: f64 = kept.iter().map(|venue| venue.tick.size).sum();
+            if weight > 0.0 {
+                kept.iter()
+                    .map(|venue| venue.tick.price * venue.tick.size)
+                    .sum::<f64>()
+                    / weight
+            } else {
+                kept.iter().map(|venue| venue.tick.price).sum::<f64>() / kept.len() as f64
+            }
+        }
+    };
+    let (size, buy_size, sell_size) = kept
+        .iter()
+        .filter_map(|venue| venue.pending)
+        .fold((0.0, 0.0, 0.0), |acc, pending| {
+            (acc.0 + pending.0, acc.1 + pending.1, acc.2 + pending.2)
+        });
+
+    Some(PriceTick {
+        exchange: config.exchange.clone(),
+        symbol: pair.clone(),
+        price,
+        size,
+        buy_size,
+        sell_size,
+        timestamp: kept.iter().map(|venue| venue.tick.timestamp).max()?,
+    })
+}
+
+/// Reads venue ticks from the live fan-out and writes a composite tick per
+/// pair every `config.tick` to `sinks`, along with candles built from them.
+/// When `cancel` fires, queued ticks and open candles are flushed first.
+pub async fn run_consolidation_task(
+    sinks: IngestionSinks,
+    mut ticks: broadcast::Receiver<PriceTick>,
+    config: ConsolidationConfig,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    let mut ticker = tokio::time::interval(config.tick);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let resolutions = config.candle_resolutions.clone();
+    let exchange = config.exchange.clone();
+    let mut outbox = Outbox::new(config.buffer_size);
+    let mut candles: HashMap<Pair, Vec<CandleBuilder>> = HashMap::new();
+    let mut consolidator = Consolidator::new(config);
+
+    loop {
+        tokio::select! {
+            tick = ticks.recv() => match tick {
+                Ok(tick) => consolidator.push(tick),
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!("Consolidation lagging, skipped {} ticks", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => {
+                let composites = consolidator.emit(Instant::now());
+                if outbox.retry(&sinks).is_err()
+                    || publish(&mut outbox, &sinks, &mut candles, &resolutions, &exchange, composites).is_err()
+                {
+                    eprintln!("Receiver dropped, stopping consolidation task");
+                    return Ok(());
+                }
+                let now = Utc::now();
+                for builder in candles.values_mut().flatten() {
+                    if let Some(candle) = builder.close_expired(now) {
+                        outbox.send_candle(&sinks, candle);
+                    }
+                }
+            }
+            _ = cancel.cancelled() => {
+                loop {
+                    match ticks.try_recv() {
+                        Ok(tick) => consolidator.push(tick),
+                        Err(TryRecvError::Lagged(_)) => {}
+                        Err(_) => break,
+                    }
+                }
+                let composites = consolidator.emit(Instant::now());
+                let published = publish(&mut outbox, &sinks, &mut candles, &resolutions, &exchange, composites);
+                for builder in candles.values_mut().flatten() {
+                    if let Some(candle) = builder.take() {
+                        outbox.send_candle(&sinks, candle);
+                    }
+                }
+                if published.is_err() || outbox.drain(&sinks).await.is_err() {
+                    eprintln!("Receiver dropped while stopping consolidation task");
+                }
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Queues composite ticks and the candles they close in `outbox`, so a full
+/// channel never holds up reading the fan-out.
+fn publish(
+    outbox: &mut Outbox,
+    sinks: &IngestionSinks,
+    candles: &mut HashMap<Pair, Vec<CandleBuilder>>,
+    resolutions: &[Resolution],
+    exchange: &Exchange,
+    ticks: Vec<PriceTick>,
+) -> Result<(), TickerError> {
+    for tick in ticks {
+        let builders = candles.entry(tick.symbol.clone()).or_insert_with(|| {
+            resolutions
+                .iter()
+                .map(|resolution| {
+                    CandleBuilder::new(exchange.clone(), tick.symbol.clone(), *resolution)
+                })
+                .collect()
+        });
+        for builder in builders {
+            if let Some(candle) = builder.push_tick(&tick) {
+                outbox.send_candle(sinks, candle);
+            }
+        }
+        outbox.send_tick(sinks, tick)?;
+    }
+    Ok(())
+}
+
+impl FromStr for ConsolidationMethod {
+    type Err = TickerError;
+
+    /// Parses `vwap` / `volume_weighted` or `median`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "vwap" | "volume_weighted" => Ok(ConsolidationMethod::VolumeWeighted),
+            "median" => Ok(ConsolidationMethod::Median),
+            _ => Err(TickerError::InvalidArgument(format!(
+                "Unknown consolidation method: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for ConsolidationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsolidationMethod::VolumeWeighted => write!(f, "vwap"),
+            ConsolidationMethod::Median => write!(f, "median"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn tick(exchange: &str, price: f64, size: f64) -> PriceTick {
+        PriceTick {
+            size,
+            timestamp: Utc::now(),
+            ..test_util::tick(exchange, "SOL-USDT", 0, price)
+        }
+    }
+
+    #[test]
+    fn leaves_out_venues_far_from_the_median() {
+        let mut consolidator = Consolidator::new(ConsolidationConfig::default());
+        consolidator.push(tick("Binance", 100.0, 1.0));
+        consolidator.push(tick("Bybit", 101.0, 1.0));
+        consolidator.push(tick("Coinbase", 150.0, 5.0));
+
+        let ticks = consolidator.emit(Instant::now());
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].exchange, Exchange::new("Consolidated"));
+        assert_eq!(ticks[0].price, 100.5);
+        assert_eq!(ticks[0].size, 2.0);
+        assert!(consolidator.emit(Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn needs_min_venues_after_filtering() {
+        let mut consolidator = Consolidator::new(ConsolidationConfig {
+            min_venues: 2,
+            ..ConsolidationConfig::default()
+        });
+        consolidator.push(tick("Binance", 100.0, 1.0));
+        consolidator.push(tick("Consolidated", 100.0, 1.0));
+        assert!(consolidator.emit(Instant::now()).is_empty());
+    }
+}
@@ -8,8 +8,14 @@ pub enum TickerError {
     RawEventParseError(String),
     #[error("Float parse error: {0}")]
     ParseDecimalError(#[from] ParseFloatError),
+    #[error("Unknown exchange: {0}")]
+    UnknownExchange(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
     #[error("Channel closed")]
     ChannelClosed,
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
+    #[error("Migration error: {0}")]
+    MigrationError(#[from] sqlx::migrate::MigrateError),
 }
@@ -1,96 +1,1038 @@
-use std::time::Duration;
+use std::{
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
-use rayon::iter::{IntoParallelIterator, ParallelIterator as _};
-use tokio::sync::mpsc;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator as _};
+use serde::Serialize;
+use tokio::{
+    sync::{
+        broadcast,
+        mpsc::{self, error::TrySendError},
+        watch,
+    },
+    time::MissedTickBehavior,
+};
 use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     adapters::ExchangeAdapter,
+    aggregation::{Aggregator, Vwap},
+    candles::CandleBuilder,
     error::TickerError,
-    types::{Event, Exchange, Pair, PriceTick},
+    retention::as_trades,
+    supervisor::{RestartPolicy, Supervisor, TaskHandle},
+    types::{
+        Candle, Event, EventStream, Exchange, Pair, PriceTick, RawPriceTick, Resolution, Side,
+        Trade,
+    },
+    windowing::EventTimeWindows,
 };
 
-pub async fn run_ingestion_task<E>(
-    tx: mpsc::Sender<PriceTick>,
-    exchange: E,
+#[derive(Debug, Clone)]
+pub struct IngestionConfig {
+    /// Events buffered between ticks before `overflow` applies. Also bounds
+    /// the output queued per channel while storage is behind.
+    pub buffer_size: usize,
+    pub overflow: OverflowPolicy,
+    pub tick: Duration,
+    pub reconnect: ReconnectPolicy,
+    /// How long a connected feed may go without trades before it is marked stale.
+    pub stale_after: Duration,
+    pub aggregator: Arc<dyn Aggregator>,
+    /// OHLCV bar widths built from the raw trades, none by default.
+    pub candle_resolutions: Vec<Resolution>,
+    pub time_mode: TimeMode,
+    /// Number of recent trade ids remembered to drop replayed trades, 0 disables.
+    pub dedup_window: usize,
+    /// Applies when the task fails, e.g. after running out of reconnect attempts.
+    pub restart: RestartPolicy,
+}
+
+/// How trades are assigned to `tick`-wide buckets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeMode {
+    /// Whatever arrived since the previous tick, stamped with the local clock.
+    #[default]
+    WallClock,
+    /// Aligned buckets by exchange timestamp, released once the watermark
+    /// passes them and stamped with the bucket start. Trades arriving after
+    /// their bucket was released are counted as late and dropped.
+    EventTime { allowed_lateness: Duration },
+}
+
+/// What happens to the event that takes the buffer past `buffer_size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered event to make room.
+    DropOldest,
+    /// Drop the incoming event.
+    DropNewest,
+    /// Aggregate and send everything buffered without waiting for the tick.
+    /// In event time this releases open buckets early, so trades arriving
+    /// for them afterwards count as late.
+    #[default]
+    EarlyFlush,
+    /// Fold the buffered trades of each bucket into one trade per side at
+    /// their aggregate price. Exact for vwap, an approximation for the
+    /// other aggregators. While folding frees less than half the buffer,
+    /// the oldest event is dropped instead until the next tick.
+    PreAggregate,
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestionSinks {
+    pub ticks: mpsc::Sender<PriceTick>,
+    /// Fan-out of every aggregated tick to live subscribers. Sending never
+    /// waits; slow receivers lag instead of holding up ingestion.
+    pub live: Option<broadcast::Sender<PriceTick>>,
+    pub candles: Option<mpsc::Sender<Candle>>,
+    pub trades: Option<mpsc::Sender<Trade>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of the delay randomly added or removed, e.g. `0.2` for ±20%.
+    pub jitter: f64,
+    /// Consecutive failed connection attempts before giving up, `None` retries forever.
+    pub max_retries: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IngestionEvent {
+    pub exchange: Exchange,
+    pub pair: Pair,
+    pub timestamp: DateTime<Utc>,
+    pub kind: IngestionEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum IngestionEventKind {
+    Connected,
+    Disconnected,
+    ConnectFailed { attempt: u32, error: String },
+    Reconnecting { attempt: u32, delay: Duration },
+    GaveUp { attempts: u32 },
+    Stale { silent_for: Duration },
+    Resumed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FeedStatus {
+    Connecting,
+    Live,
+    Stale,
+    Reconnecting,
+    Failed,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedState {
+    pub status: FeedStatus,
+    pub since: DateTime<Utc>,
+    pub last_event: Option<DateTime<Utc>>,
+    pub late_events: u64,
+    pub duplicate_events: u64,
+    /// Events dropped by the overflow policy.
+    pub dropped_events: u64,
+    /// Ticks, candles and trades dropped while storage was behind.
+    pub dropped_writes: u64,
+}
+
+pub struct IngestionHandle {
+    pub exchange: Exchange,
+    pub pair: Pair,
+    pub state: watch::Receiver<FeedState>,
+    pub task: TaskHandle,
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 100_000,
+            overflow: OverflowPolicy::default(),
+            tick: Duration::from_millis(100),
+            reconnect: ReconnectPolicy::default(),
+            stale_after: Duration::from_secs(10),
+            aggregator: Arc::new(Vwap),
+            candle_resolutions: Vec::new(),
+            time_mode: TimeMode::WallClock,
+            dedup_window: 10_000,
+            restart: RestartPolicy::default(),
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        // Capped in f64 first: the uncapped delay overflows `Duration` after
+        // enough attempts.
+        let exp = self
+            .multiplier
+            .max(1.0)
+            .powi(i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX))
+            .min(f64::MAX);
+        let secs = (self.initial_backoff.as_secs_f64() * exp).min(self.max_backoff.as_secs_f64());
+        let base = Duration::try_from_secs_f64(secs).unwrap_or(self.max_backoff);
+        // Capped again so jitter never pushes past `max_backoff`.
+        base.mul_f64(jitter_factor(self.jitter))
+            .min(self.max_backoff)
+    }
+
+    fn exhausted(&self, failures: u32) -> bool {
+        self.max_retries.is_some_and(|max| failures > max)
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = TickerError;
+
+    /// Parses `drop_oldest`, `drop_newest`, `early_flush` or `pre_aggregate`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "drop_oldest" => Ok(OverflowPolicy::DropOldest),
+            "drop_newest" => Ok(OverflowPolicy::DropNewest),
+            "early_flush" => Ok(OverflowPolicy::EarlyFlush),
+            "pre_aggregate" => Ok(OverflowPolicy::PreAggregate),
+            _ => Err(TickerError::InvalidArgument(format!(
+                "Unknown overflow policy: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverflowPolicy::DropOldest => write!(f, "drop_oldest"),
+            OverflowPolicy::DropNewest => write!(f, "drop_newest"),
+            OverflowPolicy::EarlyFlush => write!(f, "early_flush"),
+            OverflowPolicy::PreAggregate => write!(f, "pre_aggregate"),
+        }
+    }
+}
+
+impl std::fmt::Display for IngestionEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} {}] ", self.exchange, self.pair)?;
+        match &self.kind {
+            IngestionEventKind::Connected => write!(f, "connected"),
+            IngestionEventKind::Disconnected => write!(f, "stream disconnected"),
+            IngestionEventKind::ConnectFailed { attempt, error } => {
+                write!(f, "connection attempt {} failed: {}", attempt, error)
+            }
+            IngestionEventKind::Reconnecting { attempt, delay } => {
+                write!(f, "reconnecting in {:?} (attempt {})", delay, attempt)
+            }
+            IngestionEventKind::GaveUp { attempts } => {
+                write!(f, "giving up after {} attempts", attempts)
+            }
+            IngestionEventKind::Stale { silent_for } => {
+                write!(f, "stale, no trades for {:?}", silent_for)
+            }
+            IngestionEventKind::Resumed => write!(f, "trades resumed"),
+        }
+    }
+}
+
+impl IngestionEventKind {
+    fn status(&self) -> Option<FeedStatus> {
+        match self {
+            IngestionEventKind::Connected | IngestionEventKind::Resumed => Some(FeedStatus::Live),
+            IngestionEventKind::Disconnected | IngestionEventKind::Reconnecting { .. } => {
+                Some(FeedStatus::Reconnecting)
+            }
+            IngestionEventKind::GaveUp { .. } => Some(FeedStatus::Failed),
+            IngestionEventKind::Stale { .. } => Some(FeedStatus::Stale),
+            IngestionEventKind::ConnectFailed { .. } => None,
+        }
+    }
+}
+
+impl FeedState {
+    pub fn new(status: FeedStatus) -> Self {
+        Self {
+            status,
+            since: Utc::now(),
+            last_event: None,
+            late_events: 0,
+            duplicate_events: 0,
+            dropped_events: 0,
+            dropped_writes: 0,
+        }
+    }
+
+    fn set_status(&mut self, status: FeedStatus) -> bool {
+        if self.status == status {
+            return false;
+        }
+        self.status = status;
+        self.since = Utc::now();
+        true
+    }
+}
+
+impl IngestionSinks {
+    pub fn new(ticks: mpsc::Sender<PriceTick>) -> Self {
+        Self {
+            ticks,
+            live: None,
+            candles: None,
+            trades: None,
+        }
+    }
+
+    pub fn with_live(mut self, live: broadcast::Sender<PriceTick>) -> Self {
+        self.live = Some(live);
+        self
+    }
+
+    pub fn with_candles(mut self, candles: mpsc::Sender<Candle>) -> Self {
+        self.candles = Some(candles);
+        self
+    }
+
+    pub fn with_trades(mut self, trades: mpsc::Sender<Trade>) -> Self {
+        self.trades = Some(trades);
+        self
+    }
+}
+
+impl IngestionHandle {
+    pub fn status(&self) -> FeedStatus {
+        self.state.borrow().status
+    }
+
+    /// Asks the task to flush what it has buffered and exit.
+    pub fn stop(&self) {
+        self.task.stop();
+    }
+
+    /// Stops the task and waits until its buffers are flushed.
+    pub async fn shutdown(self) {
+        self.task.shutdown().await;
+    }
+}
+
+/// Runs the ingestion task under `supervisor`, restarted per `config.restart`.
+pub fn spawn_ingestion_task(
+    supervisor: &Supervisor,
+    sinks: IngestionSinks,
+    adapter: Arc<dyn ExchangeAdapter>,
     pair: Pair,
-    buffer_size: usize,
-    tick: Duration,
-) -> Result<(), TickerError>
-where
-    E: ExchangeAdapter + Send + Sync + 'static,
-{
-    let mut stream = match exchange.get_event_stream(&pair).await {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!(
-                "Error getting event stream for {:?} on {:?}: {}",
-                pair,
-                E::kind(),
-                e
-            );
-            return Err(e);
+    config: IngestionConfig,
+    events: Option<mpsc::UnboundedSender<IngestionEvent>>,
+) -> IngestionHandle {
+    let (state_tx, state) = watch::channel(FeedState::new(FeedStatus::Connecting));
+    let exchange = adapter.exchange();
+    let task = supervisor.spawn(
+        format!("ingestion {} {}", exchange, pair),
+        config.restart,
+        {
+            let pair = pair.clone();
+            move |cancel| {
+                run_ingestion_task(
+                    sinks.clone(),
+                    adapter.clone(),
+                    pair.clone(),
+                    config.clone(),
+                    state_tx.clone(),
+                    events.clone(),
+                    cancel,
+                )
+            }
+        },
+    );
+
+    IngestionHandle {
+        exchange,
+        pair,
+        state,
+        task,
+    }
+}
+
+pub async fn run_ingestion_task(
+    sinks: IngestionSinks,
+    adapter: Arc<dyn ExchangeAdapter>,
+    pair: Pair,
+    config: IngestionConfig,
+    state: watch::Sender<FeedState>,
+    events: Option<mpsc::UnboundedSender<IngestionEvent>>,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    let exchange = adapter.exchange();
+    let report = |kind: IngestionEventKind| {
+        if let Some(status) = kind.status() {
+            state.send_if_modified(|state| state.set_status(status));
+        }
+        if let Some(events) = &events {
+            let _ = events.send(IngestionEvent {
+                exchange: exchange.clone(),
+                pair: pair.clone(),
+                timestamp: Utc::now(),
+                kind,
+            });
+        }
+    };
+
+    let stopped = || {
+        state.send_if_modified(|state| state.set_status(FeedStatus::Stopped));
+    };
+    state.send_if_modified(|state| state.set_status(FeedStatus::Connecting));
+    let mut stream = tokio::select! {
+        stream = connect(adapter.as_ref(), &pair, &config.reconnect, false, &report) => stream?,
+        _ = cancel.cancelled() => {
+            stopped();
+            return Ok(());
         }
     };
-    let mut buffer = Vec::with_capacity(buffer_size);
-    let mut ticker = tokio::time::interval(tick);
+    let mut buffer = Buckets::new(&config)?;
+    let mut ticker = tokio::time::interval(config.tick);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last_activity = Instant::now();
+    let mut last_event = None;
+    let mut seen = RecentTradeIds::new(config.dedup_window);
+    let mut outbox = Outbox::new(config.buffer_size);
+    let mut dropped_events = 0;
+    // Set when pre-aggregating stops freeing room, so a buffer with more
+    // buckets than it can fold into is not rescanned on every event.
+    let mut compaction_stuck = false;
+    let mut candles: Vec<_> = config
+        .candle_resolutions
+        .iter()
+        .map(|resolution| CandleBuilder::new(exchange.clone(), pair.clone(), *resolution))
+        .collect();
 
     loop {
         tokio::select! {
-            Some(event) = stream.next() => {
-                buffer.push(event);
-                if buffer.len() > buffer_size {
-                    eprintln!("Warning: buffer size exceeded")
+            event = stream.next() => match event {
+                Some(event) => {
+                    if let Event::PriceTick(tick) = &event {
+                        if !seen.insert(tick) {
+                            continue;
+                        }
+                        last_activity = Instant::now();
+                        last_event = Some(Utc::now());
+                        if state.borrow().status == FeedStatus::Stale {
+                            report(IngestionEventKind::Resumed);
+                        }
+                    }
+                    if config.overflow == OverflowPolicy::DropNewest && buffer.len() >= config.buffer_size {
+                        dropped_events += 1;
+                        continue;
+                    }
+                    // Only accepted events reach the trade and candle sinks,
+                    // so what is stored agrees with `dropped_events`.
+                    if let Event::PriceTick(tick) = &event {
+                        outbox.send_trade(&sinks, &exchange, &pair, tick);
+                        for builder in &mut candles {
+                            if let Some(candle) = builder.push(tick) {
+                                outbox.send_candle(&sinks, candle);
+                            }
+                        }
+                    }
+                    buffer.push(event);
+                    if buffer.len() <= config.buffer_size {
+                        continue;
+                    }
+                    match config.overflow {
+                        OverflowPolicy::PreAggregate if !compaction_stuck => {
+                            buffer.compact(|events| pre_aggregate(&exchange, &pair, events, config.aggregator.as_ref()));
+                            compaction_stuck = buffer.len() > config.buffer_size / 2;
+                            if buffer.len() > config.buffer_size {
+                                buffer.pop_oldest();
+                                dropped_events += 1;
+                            }
+                        }
+                        OverflowPolicy::DropOldest | OverflowPolicy::PreAggregate => {
+                            buffer.pop_oldest();
+                            dropped_events += 1;
+                        }
+                        OverflowPolicy::DropNewest => {}
+                        OverflowPolicy::EarlyFlush => {
+                            let batches = buffer.drain_all(Utc::now());
+                            if flush(&mut outbox, &sinks, &exchange, &pair, batches, config.aggregator.as_ref()).await.is_err() {
+                                eprintln!("Receiver dropped, stopping ingestion task for {} on {}", pair, exchange);
+                                break;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    eprintln!("Event stream for {} on {} ended", pair, exchange);
+                    report(IngestionEventKind::Disconnected);
+                    let batches = buffer.drain_on_disconnect(Utc::now());
+                    if flush(&mut outbox, &sinks, &exchange, &pair, batches, config.aggregator.as_ref()).await.is_err() {
+                        eprintln!("Receiver dropped, stopping ingestion task for {} on {}", pair, exchange);
+                        break;
+                    }
+                    stream = tokio::select! {
+                        stream = connect(adapter.as_ref(), &pair, &config.reconnect, true, &report) => stream?,
+                        _ = cancel.cancelled() => {
+                            flush_all(&mut outbox, &sinks, &exchange, &pair, &mut buffer, &mut candles, config.aggregator.as_ref()).await;
+                            break;
+                        }
+                    };
+                    last_activity = Instant::now();
                 }
+            },
+            _ = cancel.cancelled() => {
+                flush_all(&mut outbox, &sinks, &exchange, &pair, &mut buffer, &mut candles, config.aggregator.as_ref()).await;
+                break;
             }
             _ = ticker.tick() => {
-                let events = std::mem::take(&mut buffer);
-                if let Some(price_tick) = par_aggregate(E::kind(), pair, Utc::now(), events).await {
-                    if tx.send(price_tick).await.is_err() {
-                        eprintln!("Receiver dropped, stopping ingestion task for {:?} on {:?}", pair, E::kind());
-                        break;
+                let now = Utc::now();
+                let batches = buffer.drain_ready(now);
+                compaction_stuck = false;
+                if outbox.retry(&sinks).is_err()
+                    || flush(&mut outbox, &sinks, &exchange, &pair, batches, config.aggregator.as_ref()).await.is_err()
+                {
+                    eprintln!("Receiver dropped, stopping ingestion task for {} on {}", pair, exchange);
+                    break;
+                }
+                let watermark = buffer.watermark(now);
+                for builder in &mut candles {
+                    if let Some(candle) = builder.close_expired(watermark) {
+                        outbox.send_candle(&sinks, candle);
                     }
                 }
+                let late_events = buffer.late_events();
+                let duplicate_events = seen.duplicates;
+                let dropped_writes = outbox.dropped();
+                state.send_if_modified(|state| {
+                    let changed = state.last_event != last_event
+                        || state.late_events != late_events
+                        || state.duplicate_events != duplicate_events
+                        || state.dropped_events != dropped_events
+                        || state.dropped_writes != dropped_writes;
+                    state.last_event = last_event;
+                    state.late_events = late_events;
+                    state.duplicate_events = duplicate_events;
+                    state.dropped_events = dropped_events;
+                    state.dropped_writes = dropped_writes;
+                    changed
+                });
+                let silent_for = last_activity.elapsed();
+                if silent_for > config.stale_after && state.borrow().status == FeedStatus::Live {
+                    report(IngestionEventKind::Stale { silent_for });
+                }
             }
         }
     }
 
+    stopped();
+    Ok(())
+}
+
+async fn flush(
+    outbox: &mut Outbox,
+    sinks: &IngestionSinks,
+    exchange: &Exchange,
+    pair: &Pair,
+    batches: Vec<(DateTime<Utc>, Vec<Event>)>,
+    aggregator: &dyn Aggregator,
+) -> Result<(), TickerError> {
+    for (ts, events) in batches {
+        if let Some(price_tick) =
+            par_aggregate(exchange.clone(), pair.clone(), ts, events, aggregator).await
+        {
+            outbox.send_tick(sinks, price_tick)?;
+        }
+    }
     Ok(())
 }
 
+/// Sends every buffered bucket and open candle, complete or not, and waits
+/// for the queued output to be accepted before the task exits.
+async fn flush_all(
+    outbox: &mut Outbox,
+    sinks: &IngestionSinks,
+    exchange: &Exchange,
+    pair: &Pair,
+    buffer: &mut Buckets,
+    candles: &mut [CandleBuilder],
+    aggregator: &dyn Aggregator,
+) {
+    let batches = buffer.drain_all(Utc::now());
+    for builder in candles {
+        if let Some(candle) = builder.take() {
+            outbox.send_candle(sinks, candle);
+        }
+    }
+    if flush(outbox, sinks, exchange, pair, batches, aggregator)
+        .await
+        .is_err()
+        || outbox.drain(sinks).await.is_err()
+    {
+        eprintln!(
+            "Receiver dropped while stopping ingestion task for {} on {}",
+            pair, exchange
+        );
+    }
+}
+
+/// Replaces the trades of one bucket by one trade per side at their
+/// aggregate price, stamped with the latest trade time.
+fn pre_aggregate(
+    exchange: &Exchange,
+    pair: &Pair,
+    events: Vec<Event>,
+    aggregator: &dyn Aggregator,
+) -> Vec<Event> {
+    let (trades, mut other): (Vec<_>, Vec<_>) = events
+        .into_iter()
+        .partition(|event| matches!(event, Event::PriceTick(_)));
+    let trades: Vec<RawPriceTick> = trades
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::PriceTick(tick) if tick.price > 0.0 && tick.size > 0.0 => Some(tick),
+            _ => None,
+        })
+        .collect();
+    let Some(latest) = trades.iter().map(|tick| tick.timestamp).max() else {
+        return other;
+    };
+    if let Some(tick) = aggregate_bucket(
+        exchange.clone(),
+        pair.clone(),
+        latest,
+        latest,
+        &trades,
+        aggregator,
+    ) {
+        other.extend(as_trades(&tick).map(Event::PriceTick));
+    }
+    other
+}
+
+/// Output waiting for room in a full channel, so a slow consumer never holds
+/// up reading the stream. Queued items are retried every tick; past
+/// `capacity` per channel the oldest are dropped.
+pub(crate) struct Outbox {
+    capacity: usize,
+    ticks: Pending<PriceTick>,
+    candles: Pending<Candle>,
+    trades: Pending<Trade>,
+}
+
+struct Pending<T> {
+    items: VecDeque<T>,
+    dropped: u64,
+}
+
+impl Outbox {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ticks: Pending::new(),
+            candles: Pending::new(),
+            trades: Pending::new(),
+        }
+    }
+
+    pub(crate) fn send_tick(
+        &mut self,
+        sinks: &IngestionSinks,
+        tick: PriceTick,
+    ) -> Result<(), TickerError> {
+        if let Some(live) = &sinks.live {
+            // Only fails when nobody is subscribed.
+            let _ = live.send(tick.clone());
+        }
+        self.ticks.offer(&sinks.ticks, tick, self.capacity)
+    }
+
+    pub(crate) fn send_candle(&mut self, sinks: &IngestionSinks, candle: Candle) {
+        if let Some(candles) = &sinks.candles
+            && self.candles.offer(candles, candle, self.capacity).is_err()
+        {
+            eprintln!("Candle receiver dropped, discarding candle");
+        }
+    }
+
+    fn send_trade(
+        &mut self,
+        sinks: &IngestionSinks,
+        exchange: &Exchange,
+        pair: &Pair,
+        tick: &RawPriceTick,
+    ) {
+        if let Some(trades) = &sinks.trades {
+            let trade = Trade {
+                exchange: exchange.clone(),
+                symbol: pair.clone(),
+                tick: tick.clone(),
+            };
+            if self.trades.offer(trades, trade, self.capacity).is_err() {
+                eprintln!("Trade receiver dropped, discarding trade");
+            }
+        }
+    }
+
+    /// Sends queued items until a channel is full again. Fails only if the
+    /// tick receiver is gone.
+    pub(crate) fn retry(&mut self, sinks: &IngestionSinks) -> Result<(), TickerError> {
+        if let Some(candles) = &sinks.candles {
+            let _ = self.candles.retry(candles);
+        }
+        if let Some(trades) = &sinks.trades {
+            let _ = self.trades.retry(trades);
+        }
+        self.ticks.retry(&sinks.ticks)
+    }
+
+    /// Waits until everything queued has been sent.
+    pub(crate) async fn drain(&mut self, sinks: &IngestionSinks) -> Result<(), TickerError> {
+        if let Some(candles) = &sinks.candles {
+            let _ = self.candles.drain(candles).await;
+        }
+        if let Some(trades) = &sinks.trades {
+            let _ = self.trades.drain(trades).await;
+        }
+        self.ticks.drain(&sinks.ticks).await
+    }
+
+    fn dropped(&self) -> u64 {
+        self.ticks.dropped + self.candles.dropped + self.trades.dropped
+    }
+}
+
+impl<T> Pending<T> {
+    fn new() -> Self {
+        Self {
+            items: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Sends `item` right away if nothing is queued ahead of it and the
+    /// channel has room, otherwise queues it.
+    fn offer(&mut self, tx: &mpsc::Sender<T>, item: T, capacity: usize) -> Result<(), TickerError> {
+        let item = if self.items.is_empty() {
+            match tx.try_send(item) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(item)) => item,
+                Err(TrySendError::Closed(_)) => return Err(TickerError::ChannelClosed),
+            }
+        } else {
+            item
+        };
+        if self.items.len() >= capacity {
+            self.items.pop_front();
+            self.dropped += 1;
+        }
+        self.items.push_back(item);
+        Ok(())
+    }
+
+    fn retry(&mut self, tx: &mpsc::Sender<T>) -> Result<(), TickerError> {
+        while let Some(item) = self.items.pop_front() {
+            match tx.try_send(item) {
+                Ok(()) => {}
+                Err(TrySendError::Full(item)) => {
+                    self.items.push_front(item);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => return Err(TickerError::ChannelClosed),
+            }
+        }
+        Ok(())
+    }
+
+    async fn drain(&mut self, tx: &mpsc::Sender<T>) -> Result<(), TickerError> {
+        while let Some(item) = self.items.pop_front() {
+            tx.send(item)
+                .await
+                .map_err(|_| TickerError::ChannelClosed)?;
+        }
+        Ok(())
+    }
+}
+
+struct RecentTradeIds {
+    capacity: usize,
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    duplicates: u64,
+}
+
+impl RecentTradeIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ids: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            duplicates: 0,
+        }
+    }
+
+    /// Returns `false` if the trade id was already seen recently.
+    fn insert(&mut self, tick: &RawPriceTick) -> bool {
+        let Some(id) = &tick.trade_id else {
+            return true;
+        };
+        if self.capacity == 0 {
+            return true;
+        }
+        if self.ids.contains(id) {
+            self.duplicates += 1;
+            return false;
+        }
+
+        if self.order.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+        self.ids.insert(id.clone());
+        self.order.push_back(id.clone());
+        true
+    }
+}
+
+enum Buckets {
+    WallClock(VecDeque<Event>),
+    EventTime(EventTimeWindows),
+}
+
+impl Buckets {
+    fn new(config: &IngestionConfig) -> Result<Self, TickerError> {
+        Ok(match config.time_mode {
+            TimeMode::WallClock => Buckets::WallClock(VecDeque::with_capacity(config.buffer_size)),
+            TimeMode::EventTime { allowed_lateness } => {
+                let resolution = Resolution::from_millis(config.tick.as_millis() as i64)?;
+                Buckets::EventTime(EventTimeWindows::new(resolution, allowed_lateness))
+            }
+        })
+    }
+
+    fn push(&mut self, event: Event) {
+        match self {
+            Buckets::WallClock(buffer) => buffer.push_back(event),
+            Buckets::EventTime(windows) => {
+                if let Err(Event::Error(err)) = windows.push(event) {
+                    eprintln!("Error event: {}", err);
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Buckets::WallClock(buffer) => buffer.len(),
+            Buckets::EventTime(windows) => windows.len(),
+        }
+    }
+
+    fn pop_oldest(&mut self) -> Option<Event> {
+        match self {
+            Buckets::WallClock(buffer) => buffer.pop_front(),
+            Buckets::EventTime(windows) => windows.pop_oldest(),
+        }
+    }
+
+    /// Rewrites the events of every bucket with `f`.
+    fn compact(&mut self, mut f: impl FnMut(Vec<Event>) -> Vec<Event>) {
+        match self {
+            Buckets::WallClock(buffer) => *buffer = f(std::mem::take(buffer).into()).into(),
+            Buckets::EventTime(windows) => windows.compact(f),
+        }
+    }
+
+    /// Time before which trades are taken as complete, which decides when
+    /// candles close: the local clock unless bucketing by event time.
+    fn watermark(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Buckets::WallClock(_) => now,
+            Buckets::EventTime(windows) => windows.watermark(now),
+        }
+    }
+
+    fn late_events(&self) -> u64 {
+        match self {
+            Buckets::WallClock(_) => 0,
+            Buckets::EventTime(windows) => windows.late_events(),
+        }
+    }
+
+    fn drain_ready(&mut self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, Vec<Event>)> {
+        match self {
+            Buckets::WallClock(buffer) => vec![(now, std::mem::take(buffer).into())],
+            Buckets::EventTime(windows) => windows.drain_closed(now),
+        }
+    }
+
+    fn drain_all(&mut self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, Vec<Event>)> {
+        match self {
+            Buckets::WallClock(_) => self.drain_ready(now),
+            Buckets::EventTime(windows) => windows.drain_all(),
+        }
+    }
+
+    /// Event-time buckets stay open across a reconnect since the watermark
+    /// decides when they are complete.
+    fn drain_on_disconnect(&mut self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, Vec<Event>)> {
+        match self {
+            Buckets::WallClock(_) => self.drain_ready(now),
+            Buckets::EventTime(_) => Vec::new(),
+        }
+    }
+}
+
+async fn connect<'a>(
+    adapter: &'a dyn ExchangeAdapter,
+    pair: &Pair,
+    policy: &ReconnectPolicy,
+    reconnecting: bool,
+    report: &impl Fn(IngestionEventKind),
+) -> Result<EventStream<'a, Event>, TickerError> {
+    let mut failures = 0;
+    loop {
+        if reconnecting || failures > 0 {
+            let attempt = failures + 1;
+            let delay = policy.backoff(attempt);
+            report(IngestionEventKind::Reconnecting { attempt, delay });
+            tokio::time::sleep(delay).await;
+        }
+
+        match adapter.get_event_stream(pair).await {
+            Ok(stream) => {
+                report(IngestionEventKind::Connected);
+                return Ok(stream);
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!(
+                    "Error getting event stream for {} on {}: {}",
+                    pair,
+                    adapter.exchange(),
+                    e
+                );
+                report(IngestionEventKind::ConnectFailed {
+                    attempt: failures,
+                    error: e.to_string(),
+                });
+                if policy.exhausted(failures) {
+                    report(IngestionEventKind::GaveUp { attempts: failures });
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+fn jitter_factor(jitter: f64) -> f64 {
+    1.0 + jitter.clamp(0.0, 1.0) * (2.0 * rand::random::<f64>() - 1.0)
+}
+
 async fn par_aggregate(
     exchange: Exchange,
     pair: Pair,
     ts: DateTime<Utc>,
     events: Vec<Event>,
+    aggregator: &dyn Aggregator,
 ) -> Option<PriceTick> {
-    let (weighted_sum_price, total_size) = events
+    let trades: Vec<RawPriceTick> = events
         .into_par_iter()
         .filter_map(|event| match event {
-            Event::PriceTick(tick) if tick.price > 0.0 && tick.size > 0.0 => {
-                Some((tick.price * tick.size, tick.size))
-            }
+            Event::PriceTick(tick) if tick.price > 0.0 && tick.size > 0.0 => Some(tick),
             Event::Error(err) => {
-                eprintln!("Error event from {:?} on {:?}: {}", exchange, pair, err);
+                eprintln!("Error event from {} on {}: {}", exchange, pair, err);
                 None
             }
             _ => None,
         })
+        .collect();
+
+    aggregate_bucket(exchange, pair, ts, ts, &trades, aggregator)
+}
+
+/// Reduces the trades of one bucket to a tick stamped `timestamp`, splitting
+/// the volume by aggressor side. `end` is passed through to the aggregator.
+pub(crate) fn aggregate_bucket(
+    exchange: Exchange,
+    pair: Pair,
+    timestamp: DateTime<Utc>,
+    end: DateTime<Utc>,
+    trades: &[RawPriceTick],
+    aggregator: &dyn Aggregator,
+) -> Option<PriceTick> {
+    let (total_size, buy_size, sell_size) = trades
+        .par_iter()
+        .map(|tick| match tick.side {
+            Some(Side::Buy) => (tick.size, tick.size, 0.0),
+            Some(Side::Sell) => (tick.size, 0.0, tick.size),
+            None => (tick.size, 0.0, 0.0),
+        })
         .reduce(
-            || (0.0, 0.0),
-            |(sum_vw, sum_w), (vw, w)| (sum_vw + vw, sum_w + w),
+            || (0.0, 0.0, 0.0),
+            |(total, buy, sell), (t, b, s)| (total + t, buy + b, sell + s),
         );
 
     if total_size > 0.0 {
         Some(PriceTick {
+            price: aggregator.aggregate(trades, end)?,
             exchange,
             symbol: pair,
-            price: weighted_sum_price / total_size,
             size: total_size,
-            timestamp: ts,
+            buy_size,
+            sell_size,
+            timestamp,
         })
     } else {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_then_caps() {
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..ReconnectPolicy::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        for attempt in [7, 64, 1_000, i32::MAX as u32 + 2, u32::MAX] {
+            assert_eq!(policy.backoff(attempt), policy.max_backoff);
+        }
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_band() {
+        let policy = ReconnectPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            let base = policy.initial_backoff;
+            assert!(delay >= base.mul_f64(0.8) && delay <= base.mul_f64(1.2));
+
+            let delay = policy.backoff(u32::MAX);
+            assert!(delay >= policy.max_backoff.mul_f64(0.8) && delay <= policy.max_backoff);
+        }
+    }
+}
@@ -1,5 +1,14 @@
 pub mod adapters;
+pub mod aggregation;
+pub mod candles;
+pub mod consolidation;
 pub mod error;
 pub mod ingestion;
+pub mod registry;
+pub mod retention;
 pub mod storage;
+pub mod supervisor;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod types;
+pub mod windowing;
@@ -0,0 +1,108 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{
+    adapters::{BinanceAdapter, BybitAdapter, CoinbaseAdapter, ExchangeAdapter},
+    error::TickerError,
+    types::{Exchange, Pair, PairFormat},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeInfo {
+    pub exchange: Exchange,
+    pub display_name: String,
+    pub pair_format: PairFormat,
+    /// Pairs the venue is known to list; empty means any pair is accepted.
+    pub pairs: Vec<Pair>,
+}
+
+impl ExchangeInfo {
+    pub fn new(
+        id: impl Into<String>,
+        display_name: impl Into<String>,
+        pair_format: PairFormat,
+    ) -> Self {
+        Self {
+            exchange: Exchange::new(id),
+            display_name: display_name.into(),
+            pair_format,
+            pairs: Vec::new(),
+        }
+    }
+
+    pub fn with_pairs(mut self, pairs: impl IntoIterator<Item = Pair>) -> Self {
+        self.pairs = pairs.into_iter().collect();
+        self
+    }
+
+    pub fn symbol(&self, pair: &Pair) -> String {
+        pair.format(self.pair_format)
+    }
+
+    pub fn supports(&self, pair: &Pair) -> bool {
+        self.pairs.is_empty() || self.pairs.contains(pair)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ExchangeRegistry {
+    exchanges: HashMap<String, ExchangeInfo>,
+    adapters: HashMap<String, Arc<dyn ExchangeAdapter>>,
+}
+
+impl ExchangeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register_adapter(Arc::new(BinanceAdapter));
+        registry.register_adapter(Arc::new(BybitAdapter));
+        registry.register_adapter(Arc::new(CoinbaseAdapter));
+        registry
+    }
+
+    pub fn register(&mut self, info: ExchangeInfo) -> Option<ExchangeInfo> {
+        self.exchanges
+            .insert(info.exchange.as_str().to_string(), info)
+    }
+
+    pub fn register_adapter(&mut self, adapter: Arc<dyn ExchangeAdapter>) -> Option<ExchangeInfo> {
+        let info = adapter.info();
+        self.adapters
+            .insert(info.exchange.as_str().to_string(), adapter);
+        self.register(info)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ExchangeInfo> {
+        self.exchanges.get(id)
+    }
+
+    pub fn adapter(&self, id: &str) -> Result<Arc<dyn ExchangeAdapter>, TickerError> {
+        self.adapters
+            .get(id)
+            .cloned()
+            .ok_or_else(|| TickerError::UnknownExchange(id.to_string()))
+    }
+
+    pub fn resolve(&self, id: &str) -> Result<Exchange, TickerError> {
+        self.get(id)
+            .map(|info| info.exchange.clone())
+            .ok_or_else(|| TickerError::UnknownExchange(id.to_string()))
+    }
+
+    pub fn exchanges(&self) -> impl Iterator<Item = &ExchangeInfo> {
+        self.exchanges.values()
+    }
+}
+
+impl std::fmt::Debug for ExchangeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExchangeRegistry")
+            .field("exchanges", &self.exchanges)
+            .field("adapters", &self.adapters.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
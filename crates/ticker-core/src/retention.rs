@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    aggregation::{Aggregator, Vwap},
+    error::TickerError,
+    ingestion::aggregate_bucket,
+    storage::{SortOrder, TickQuery, TickStore},
+    types::{Exchange, Pair, PriceTick, RawPriceTick, Resolution, Side},
+};
+
+const ROLLUP_PAGE_SIZE: usize = 10_000;
+
+/// One storage tier: ticks of `resolution` are kept for `keep_for` and then
+/// rolled up into the next tier, or deleted if this is the last one.
+/// `None` keeps them forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionTier {
+    pub resolution: Resolution,
+    pub keep_for: Option<Duration>,
+}
+
+/// Tiers from finest to coarsest. The first tier is the raw `price_ticks`
+/// table written by ingestion, so its resolution should match the tick interval.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    tiers: Vec<RetentionTier>,
+    pub interval: Duration,
+    pub aggregator: Arc<dyn Aggregator>,
+    /// Cutoff each tier was last rolled up to, shared by every clone.
+    rolled_up_to: Arc<Mutex<Vec<Option<DateTime<Utc>>>>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub rolled_up: u64,
+    pub deleted: u64,
+}
+
+impl RetentionPolicy {
+    pub fn new(tiers: Vec<RetentionTier>) -> Result<Self, TickerError> {
+        if tiers.is_empty() {
+            return Err(TickerError::InvalidArgument(
+                "Retention policy needs at least one tier".to_string(),
+            ));
+        }
+        for pair in tiers.windows(2) {
+            let (finer, coarser) = (pair[0].resolution, pair[1].resolution);
+            if coarser <= finer || coarser.as_millis() % finer.as_millis() != 0 {
+                return Err(TickerError::InvalidArgument(format!(
+                    "Retention tier {} is not a coarser multiple of {}",
+                    coarser, finer
+                )));
+            }
+        }
+
+        Ok(Self {
+            rolled_up_to: Arc::new(Mutex::new(vec![None; tiers.len()])),
+            tiers,
+            interval: Duration::from_secs(60),
+            aggregator: Arc::new(Vwap),
+        })
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_aggregator(mut self, aggregator: Arc<dyn Aggregator>) -> Self {
+        self.aggregator = aggregator;
+        self
+    }
+
+    pub fn tiers(&self) -> &[RetentionTier] {
+        &self.tiers
+    }
+
+    /// Time before which the ticks of the tier at `index` have moved to the
+    /// next tier: the cutoff of the last retention pass through it, or the
+    /// cutoff as of `now` before the first. `None` if the tier is kept forever.
+    pub fn tier_boundary(&self, index: usize, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let rolled_up_to = self.rolled_up_to.lock().unwrap().get(index).copied()?;
+        rolled_up_to.or_else(|| retention_cutoff(self, index, now))
+    }
+}
+
+/// Applies `policy` every `policy.interval` until `cancel` fires; a pass in
+/// progress is finished first.
+pub async fn run_retention_task(
+    store: Arc<dyn TickStore>,
+    policy: RetentionPolicy,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    let mut ticker = tokio::time::interval(policy.interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = cancel.cancelled() => return Ok(()),
+        }
+        match apply_retention(store.as_ref(), &policy, Utc::now()).await {
+            Ok(report) if report != RetentionReport::default() => println!(
+                "Retention: rolled up {} ticks, deleted {}",
+                report.rolled_up, report.deleted
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("Error applying retention policy: {}", e),
+        }
+    }
+}
+
+/// Runs one retention pass, finest tier first. Cutoffs are aligned to the
+/// next tier's buckets so only complete buckets are rolled up.
+pub async fn apply_retention(
+    store: &dyn TickStore,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<RetentionReport, TickerError> {
+    let mut report = RetentionReport::default();
+
+    for (i, tier) in policy.tiers.iter().enumerate() {
+        let Some(cutoff) = retention_cutoff(policy, i, now) else {
+            continue;
+        };
+
+        let source = (i > 0).then_some(tier.resolution);
+        if let Some(next) = policy.tiers.get(i + 1) {
+            report.rolled_up += roll_up(
+                store,
+                source,
+                next.resolution,
+                cutoff,
+                policy.aggregator.as_ref(),
+            )
+            .await?;
+        }
+        report.deleted += store.delete_before(source, cutoff).await?;
+        policy.rolled_up_to.lock().unwrap()[i] = Some(cutoff);
+    }
+
+    Ok(report)
+}
+
+/// Time before which the ticks of the tier at `index` are rolled up into the
+/// next tier, or deleted from the last one, as of `now`. Aligned to the next
+/// tier's buckets. `None` if the tier is kept forever.
+pub fn retention_cutoff(
+    policy: &RetentionPolicy,
+    index: usize,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let keep_for = chrono::Duration::from_std(policy.tiers.get(index)?.keep_for?).ok()?;
+    let cutoff = now.checked_sub_signed(keep_for)?;
+    Some(match policy.tiers.get(index + 1) {
+        Some(next) => next.resolution.bucket_start(cutoff),
+        None => cutoff,
+    })
+}
+
+/// Aggregates every `source` tick before `cutoff` into `target` buckets and
+/// returns how many source ticks were read.
+async fn roll_up(
+    store: &dyn TickStore,
+    source: Option<Resolution>,
+    target: Resolution,
+    cutoff: DateTime<Utc>,
+    aggregator: &dyn Aggregator,
+) -> Result<u64, TickerError> {
+    let mut query = TickQuery::new()
+        .with_resolution(source)
+        .with_to(cutoff)
+        .with_order(SortOrder::Asc)
+        .with_limit(ROLLUP_PAGE_SIZE);
+    let mut open: HashMap<(Exchange, Pair), (DateTime<Utc>, Vec<RawPriceTick>)> = HashMap::new();
+    let mut read = 0;
+
+    loop {
+        let page = store.query_ticks(&query).await?;
+        read += page.ticks.len() as u64;
+
+        let mut rollups = Vec::new();
+        for tick in page.ticks {
+            let start = target.bucket_start(tick.timestamp);
+            let key = (tick.exchange.clone(), tick.symbol.clone());
+            let bucket = open
+                .entry(key.clone())
+                .or_insert_with(|| (start, Vec::new()));
+            if bucket.0 != start {
+                let (start, trades) = std::mem::replace(bucket, (start, Vec::new()));
+                rollups.extend(close_bucket(key, start, target, &trades, aggregator));
+            }
+            bucket.1.extend(as_trades(&tick));
+        }
+
+        let Some(cursor) = page.next_cursor else {
+            rollups.extend(open.drain().filter_map(|(key, (start, trades))| {
+                close_bucket(key, start, target, &trades, aggregator)
+            }));
+            store.insert_rollups(target, &rollups).await?;
+            return Ok(read);
+        };
+        store.insert_rollups(target, &rollups).await?;
+        query = query.with_cursor(cursor);
+    }
+}
+
+fn close_bucket(
+    (exchange, pair): (Exchange, Pair),
+    start: DateTime<Utc>,
+    resolution: Resolution,
+    trades: &[RawPriceTick],
+    aggregator: &dyn Aggregator,
+) -> Option<PriceTick> {
+    let end = resolution.bucket_end(start);
+    aggregate_bucket(exchange, pair, start, end, trades, aggregator)
+}
+
+/// Splits an aggregated tick back into sided trades so the rollup keeps the
+/// buy/sell volume split and size-weighted aggregators stay exact.
+pub(crate) fn as_trades(tick: &PriceTick) -> impl Iterator<Item = RawPriceTick> + '_ {
+    let unsided = (tick.size - tick.buy_size - tick.sell_size).max(0.0);
+    [
+        (Some(Side::Buy), tick.buy_size),
+        (Some(Side::Sell), tick.sell_size),
+        (None, unsided),
+    ]
+    .into_iter()
+    .filter(|(_, size)| *size > 0.0)
+    .map(move |(side, size)| RawPriceTick {
+        price: tick.price,
+        size,
+        timestamp: tick.timestamp,
+        received_at: tick.timestamp,
+        side,
+        trade_id: None,
+        sequence: None,
+    })
+}
+
+impl FromStr for RetentionTier {
+    type Err = TickerError;
+
+    /// Parses `<resolution>:<keep for>` such as `1s:30d`, or a bare
+    /// resolution to keep that tier forever.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (resolution, keep_for) = match s.split_once(':') {
+            Some((resolution, keep_for)) => (resolution, Some(keep_for)),
+            None => (s, None),
+        };
+
+        Ok(Self {
+            resolution: resolution.parse()?,
+            keep_for: keep_for
+                .map(|keep_for| keep_for.parse::<Resolution>())
+                .transpose()?
+                .map(|keep_for| keep_for.as_duration()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::MemoryStore,
+        test_util::{at, tick},
+    };
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy::new(vec!["100ms:1h".parse().unwrap(), "1s".parse().unwrap()]).unwrap()
+    }
+
+    #[test]
+    fn cutoff_is_aligned_to_the_next_tier() {
+        let policy = policy();
+        let now = at(1_800_000_000_750);
+        assert_eq!(
+            retention_cutoff(&policy, 0, now),
+            Some(at(1_800_000_000_000 - 3_600_000))
+        );
+        assert_eq!(retention_cutoff(&policy, 1, now), None);
+    }
+
+    #[tokio::test]
+    async fn rollup_keeps_volume_totals() {
+        let store = MemoryStore::new();
+        let now = at(1_800_000_000_000);
+        let old = now - chrono::Duration::hours(2);
+        let ticks: Vec<_> = (0..20)
+            .map(|i| PriceTick {
+                size: 1.0 + (i % 3) as f64,
+                buy_size: (i % 3) as f64,
+                sell_size: 1.0,
+                timestamp: old + chrono::Duration::milliseconds(100 * i),
+                ..tick("Binance", "SOL-USDT", 0, 100.0 + i as f64)
+            })
+            .collect();
+        let recent = PriceTick {
+            timestamp: now,
+            ..ticks[0].clone()
+        };
+        store.insert_ticks(&ticks).await.unwrap();
+        store.insert_ticks(&[recent]).await.unwrap();
+
+        let policy = policy();
+        let later = now + chrono::Duration::minutes(5);
+        assert_eq!(
+            policy.tier_boundary(0, later),
+            retention_cutoff(&policy, 0, later)
+        );
+        let report = apply_retention(&store, &policy, now).await.unwrap();
+        assert_eq!(
+            report,
+            RetentionReport {
+                rolled_up: 20,
+                deleted: 20
+            }
+        );
+
+        assert_eq!(
+            policy.tier_boundary(0, later),
+            retention_cutoff(&policy, 0, now)
+        );
+
+        let raw = store.query_ticks(&TickQuery::new()).await.unwrap().ticks;
+        assert_eq!(raw.len(), 1);
+        let second = Resolution::from_millis(1_000).unwrap();
+        let rollups = store
+            .query_ticks(&TickQuery::new().with_resolution(Some(second)))
+            .await
+            .unwrap()
+            .ticks;
+        assert_eq!(rollups.len(), 2);
+
+        let total =
+            |ticks: &[PriceTick], f: fn(&PriceTick) -> f64| ticks.iter().map(f).sum::<f64>();
+        assert_eq!(total(&rollups, |t| t.size), total(&ticks, |t| t.size));
+        assert_eq!(
+            total(&rollups, |t| t.buy_size),
+            total(&ticks, |t| t.buy_size)
+        );
+        assert_eq!(
+            total(&rollups, |t| t.sell_size),
+            total(&ticks, |t| t.sell_size)
+        );
+        let notional = |t: &PriceTick| t.price * t.size;
+        assert!((total(&rollups, notional) - total(&ticks, notional)).abs() < 1e-6);
+    }
+}
@@ -1,100 +1,509 @@
-use sqlx::SqlitePool;
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod query;
+mod sqlite;
+
+pub use memory::*;
+#[cfg(feature = "postgres")]
+pub use postgres::*;
+pub use query::*;
+pub use sqlite::*;
+
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
-use crate::{error::TickerError, types::PriceTick};
+use crate::{
+    error::TickerError,
+    types::{Candle, Pair, PriceTick, Resolution, Trade},
+};
 
-pub async fn run_db_task(
-    db: SqlitePool,
-    mut rx: mpsc::Receiver<PriceTick>,
-) -> Result<(), TickerError> {
-    create_tables(&db).await?;
+// SQLite caps bound parameters per statement at 32766.
+const MAX_ROWS_PER_INSERT: usize = 1000;
+
+// Upper bound on the doubling delay between write retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Re-running a rollup over the same source rows overwrites rather than duplicates.
+const ROLLUP_UPSERT: &str = " ON CONFLICT (exchange, symbol, resolution_ms, ts) DO UPDATE SET \
+    price = excluded.price, sz = excluded.sz, buy_sz = excluded.buy_sz, sell_sz = excluded.sell_sz";
+
+// Tables whose `symbol` column holds a stored pair.
+const SYMBOL_TABLES: &[&str] = &["price_ticks", "price_tick_rollups", "candles", "trades"];
+
+type InsertFuture<'a> = Pin<Box<dyn Future<Output = Result<(), TickerError>> + Send + 'a>>;
+
+#[async_trait::async_trait]
+pub trait TickStore: Send + Sync {
+    /// Brings the schema up to date by applying any pending migrations.
+    async fn migrate(&self) -> Result<(), TickerError>;
+
+    async fn insert_ticks(&self, ticks: &[PriceTick]) -> Result<(), TickerError>;
 
-    while let Some(tick) = rx.recv().await {
-        match store_event(&db, tick).await {
-            Ok(_) => (),
-            Err(e) => eprintln!("Error storing price tick: {}", e),
+    async fn insert_candles(&self, candles: &[Candle]) -> Result<(), TickerError>;
+
+    async fn insert_trades(&self, trades: &[Trade]) -> Result<(), TickerError>;
+
+    async fn query_ticks(&self, query: &TickQuery) -> Result<TickPage, TickerError>;
+
+    /// Reads bars written by the candle DB task.
+    async fn query_candles(&self, query: &CandleQuery) -> Result<Vec<Candle>, TickerError>;
+
+    /// Writes downsampled ticks, replacing any rollup already stored for the
+    /// same exchange, pair, resolution and timestamp.
+    async fn insert_rollups(
+        &self,
+        resolution: Resolution,
+        ticks: &[PriceTick],
+    ) -> Result<(), TickerError>;
+
+    /// Deletes raw ticks, or the rollups of `resolution`, older than `before`,
+    /// returning how many were removed.
+    async fn delete_before(
+        &self,
+        resolution: Option<Resolution>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, TickerError>;
+}
+
+/// Bounds on how many rows a DB task accumulates before writing, and how long
+/// the first row of a batch may wait.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    pub max_size: usize,
+    pub max_latency: Duration,
+    /// Failed writes are retried this many times before the task gives up.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after each failure.
+    pub retry_delay: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 500,
+            max_latency: Duration::from_millis(250),
+            max_retries: 5,
+            retry_delay: Duration::from_millis(500),
         }
     }
-    Ok(())
 }
 
-pub async fn create_tables(db: &SqlitePool) -> Result<(), TickerError> {
-    sqlx::query(include_str!("../queries/schema.sql"))
-        .execute(db)
-        .await?;
-    Ok(())
+impl BatchConfig {
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let exp = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.retry_delay.saturating_mul(exp).min(MAX_RETRY_DELAY)
+    }
 }
 
-pub async fn store_event(db: &SqlitePool, tick: PriceTick) -> Result<(), TickerError> {
-    let (exchange, symbol, price, size, timestamp) = tick.into_strings();
+/// Opens the backend matching the URL scheme: `sqlite:`, `memory:` or, with
+/// the `postgres` feature, `postgres://`. Pending migrations are applied.
+pub async fn connect(url: &str) -> Result<Arc<dyn TickStore>, TickerError> {
+    let store: Arc<dyn TickStore> = match url.split_once(':').map(|(scheme, _)| scheme) {
+        Some("sqlite") => Arc::new(SqliteStore::connect(url).await?),
+        Some("memory") => Arc::new(MemoryStore::new()),
+        #[cfg(feature = "postgres")]
+        Some("postgres" | "postgresql") => Arc::new(PgStore::connect(url).await?),
+        _ => {
+            return Err(TickerError::InvalidArgument(format!(
+                "Unsupported database URL: {}",
+                url
+            )));
+        }
+    };
 
-    sqlx::query_file!(
-        "queries/insert_price_tick.sql",
-        exchange,
-        symbol,
-        price,
-        size,
-        timestamp,
-    )
-    .execute(db)
-    .await?;
+    store.migrate().await?;
+    Ok(store)
+}
 
+/// Borrows `rx` so a restarted task carries on with the same channel. Once
+/// `cancel` fires, whatever is still queued is written before returning. A
+/// batch that still fails after `batch.max_retries` retries is dropped and
+/// its error returned.
+pub async fn run_db_task(
+    store: Arc<dyn TickStore>,
+    rx: &mut mpsc::Receiver<PriceTick>,
+    batch: BatchConfig,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    while let Some(ticks) = recv_batch(rx, &batch, &cancel).await {
+        write_batch(&*store, &ticks, &batch, "price ticks", |store, ticks| {
+            store.insert_ticks(ticks)
+        })
+        .await?;
+    }
     Ok(())
 }
 
-pub async fn select_price_ticks(
-    db: &SqlitePool,
-    exchange: &str,
-    symbol: &str,
-    limit: i64,
-) -> Result<Vec<PriceTick>, TickerError> {
-    let rows = sqlx::query_file!("queries/select_price_ticks.sql", exchange, symbol, limit)
-        .fetch_all(db)
+pub async fn run_candle_db_task(
+    store: Arc<dyn TickStore>,
+    rx: &mut mpsc::Receiver<Candle>,
+    batch: BatchConfig,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    while let Some(candles) = recv_batch(rx, &batch, &cancel).await {
+        write_batch(&*store, &candles, &batch, "candles", |store, candles| {
+            store.insert_candles(candles)
+        })
         .await?;
+    }
+    Ok(())
+}
 
-    let ticks = rows
-        .into_iter()
-        .map(|row| {
-            PriceTick::try_from_db_record(row.exchange, row.symbol, row.price, row.sz, row.ts)
+pub async fn run_trade_db_task(
+    store: Arc<dyn TickStore>,
+    rx: &mut mpsc::Receiver<Trade>,
+    batch: BatchConfig,
+    cancel: CancellationToken,
+) -> Result<(), TickerError> {
+    while let Some(trades) = recv_batch(rx, &batch, &cancel).await {
+        write_batch(&*store, &trades, &batch, "trades", |store, trades| {
+            store.insert_trades(trades)
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .await?;
+    }
+    Ok(())
+}
 
-    Ok(ticks)
+/// Writes `items`, retrying with a doubling delay while `insert` fails.
+async fn write_batch<T>(
+    store: &dyn TickStore,
+    items: &[T],
+    batch: &BatchConfig,
+    what: &str,
+    insert: for<'a> fn(&'a dyn TickStore, &'a [T]) -> InsertFuture<'a>,
+) -> Result<(), TickerError> {
+    let mut attempt = 0;
+    loop {
+        match insert(store, items).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < batch.max_retries => {
+                attempt += 1;
+                let delay = batch.retry_delay(attempt);
+                eprintln!(
+                    "Error storing batch of {} {}: {}, retrying in {:?}",
+                    items.len(),
+                    what,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Dropping batch of {} {} after {} retries: {}",
+                    items.len(),
+                    what,
+                    attempt,
+                    e
+                );
+                return Err(e);
+            }
+        }
+    }
 }
 
-pub async fn select_all_price_ticks(
-    db: &SqlitePool,
-    limit: i64,
-) -> Result<Vec<PriceTick>, TickerError> {
-    let rows = sqlx::query_file!("queries/select_all_price_ticks.sql", limit)
-        .fetch_all(db)
-        .await?;
+/// Maps concatenated symbols written before pairs were stored as
+/// `BASE-QUOTE` onto their dashed form, splitting them the way [`Pair`]
+/// parses them. Fails, naming every symbol it could not split, rather than
+/// leave rows that no longer read back.
+fn dashed_symbols(symbols: Vec<String>) -> Result<Vec<(String, String)>, TickerError> {
+    let mut unsplit = Vec::new();
+    let mut renames = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        match symbol.parse::<Pair>() {
+            Ok(pair) => renames.push((symbol, pair.to_string())),
+            Err(_) => unsplit.push(symbol),
+        }
+    }
+    if !unsplit.is_empty() {
+        return Err(TickerError::InvalidArgument(format!(
+            "Cannot split stored symbols into base and quote: {}",
+            unsplit.join(", ")
+        )));
+    }
+    Ok(renames)
+}
 
-    let ticks = rows
-        .into_iter()
-        .map(|row| {
-            PriceTick::try_from_db_record(row.exchange, row.symbol, row.price, row.sz, row.ts)
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+/// Waits for one item, then keeps collecting until the batch is full or
+/// `max_latency` has passed. Returns `None` once the channel is closed and
+/// empty; `cancel` closes it so the remaining items can be drained.
+pub async fn recv_batch<T>(
+    rx: &mut mpsc::Receiver<T>,
+    batch: &BatchConfig,
+    cancel: &CancellationToken,
+) -> Option<Vec<T>> {
+    let first = tokio::select! {
+        item = rx.recv() => item?,
+        _ = cancel.cancelled() => {
+            rx.close();
+            rx.recv().await?
+        }
+    };
+    let mut items = Vec::with_capacity(batch.max_size.max(1));
+    items.push(first);
 
-    Ok(ticks)
+    let deadline = tokio::time::Instant::now() + batch.max_latency;
+    while items.len() < batch.max_size {
+        match tokio::time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some(item)) => items.push(item),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    Some(items)
 }
 
-pub async fn select_price_ticks_after(
-    db: &SqlitePool,
-    secs: i64,
-) -> Result<Vec<PriceTick>, TickerError> {
-    let ts = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(secs);
-    let rows = sqlx::query_file!("queries/select_price_ticks_after.sql", ts)
-        .fetch_all(db)
-        .await?;
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::{
+        test_util::{at, tick},
+        types::Exchange,
+    };
+
+    /// Runs every contract check, each against a fresh store from `open`.
+    async fn check_contract(name: &str, open: impl AsyncFn() -> Arc<dyn TickStore>) {
+        cursor_pages_through_equal_timestamps(name, &*open().await).await;
+        filters_ticks_and_round_trips_them(name, &*open().await).await;
+        rollups_replace_and_delete_by_tier(name, &*open().await).await;
+        candles_round_trip_by_resolution(name, &*open().await).await;
+    }
+
+    #[tokio::test]
+    async fn memory_store_meets_the_contract() {
+        check_contract("memory", async || Arc::new(MemoryStore::new()) as _).await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_store_meets_the_contract() {
+        check_contract("sqlite", async || Arc::new(sqlite_store().await) as _).await;
+    }
+
+    /// Runs on the Postgres at `DATABASE_URL`, in a schema of its own that is
+    /// dropped afterwards, and is skipped unless that is a Postgres URL. The
+    /// checked SQLite queries read `DATABASE_URL` when compiled too, so build
+    /// the tests first and run them with `--lib`.
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn postgres_store_meets_the_contract() {
+        use sqlx::postgres::{PgConnectOptions, PgPool};
+
+        let url = match std::env::var("DATABASE_URL") {
+            Ok(url) if url.starts_with("postgres") => url,
+            _ => {
+                eprintln!("DATABASE_URL is not a Postgres URL, skipping");
+                return;
+            }
+        };
+        let schema = format!(
+            "ticker_contract_{}_{}",
+            std::process::id(),
+            Utc::now().timestamp_micros()
+        );
+        let admin = PgPool::connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema))
+            .execute(&admin)
+            .await
+            .unwrap();
 
-    let ticks = rows
-        .into_iter()
-        .map(|row| {
-            PriceTick::try_from_db_record(row.exchange, row.symbol, row.price, row.sz, row.ts)
+        let options = url
+            .parse::<PgConnectOptions>()
+            .unwrap()
+            .options([("search_path", &schema)]);
+        let store = PgStore::new(PgPool::connect_with(options).await.unwrap());
+        store.migrate().await.unwrap();
+        check_contract("postgres", async || {
+            sqlx::query(&format!("TRUNCATE {}", SYMBOL_TABLES.join(", ")))
+                .execute(&store.db)
+                .await
+                .unwrap();
+            Arc::new(store.clone()) as _
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .await;
 
-    Ok(ticks)
+        store.db.close().await;
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema))
+            .execute(&admin)
+            .await
+            .unwrap();
+    }
+
+    async fn sqlite_store() -> SqliteStore {
+        // One connection kept forever, or each would see its own database.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let sqlite = SqliteStore::new(pool);
+        sqlite.migrate().await.unwrap();
+        sqlite
+    }
+
+    async fn cursor_pages_through_equal_timestamps(name: &str, store: &dyn TickStore) {
+        // Five ticks share a timestamp, so only the id orders them.
+        let mut ticks: Vec<_> = (1..=5)
+            .map(|i| tick("Binance", "SOL-USDT", 1_000, i as f64))
+            .collect();
+        ticks.extend([6.0, 7.0].map(|price| tick("Binance", "SOL-USDT", 2_000, price)));
+        store.insert_ticks(&ticks).await.unwrap();
+
+        for order in [SortOrder::Asc, SortOrder::Desc] {
+            let mut query = TickQuery::new().with_order(order).with_limit(2);
+            let mut prices = Vec::new();
+            loop {
+                let page = store.query_ticks(&query).await.unwrap();
+                assert!(page.ticks.len() <= 2);
+                prices.extend(page.ticks.iter().map(|tick| tick.price));
+                let Some(cursor) = page.next_cursor else {
+                    break;
+                };
+                query = query.with_cursor(cursor);
+            }
+            let mut expected: Vec<_> = (1..=7).map(f64::from).collect();
+            if order == SortOrder::Desc {
+                expected.reverse();
+            }
+            assert_eq!(prices, expected, "{} {:?}", name, order);
+        }
+    }
+
+    async fn filters_ticks_and_round_trips_them(name: &str, store: &dyn TickStore) {
+        store
+            .insert_ticks(&[
+                tick("Binance", "SOL-DAI", 1_000, 1.0),
+                tick("Binance", "SOL-USDT", 1_500, 2.0),
+                tick("Bybit", "SOL-DAI", 2_000, 3.0),
+                tick("Binance", "SOL-DAI", 3_000, 4.0),
+            ])
+            .await
+            .unwrap();
+
+        let query = TickQuery::new()
+            .with_exchanges([Exchange::new("Binance")])
+            .with_pairs([Pair::new("SOL", "DAI")])
+            .with_from(at(1_000))
+            .with_to(at(3_000));
+        let ticks = store.query_ticks(&query).await.unwrap().ticks;
+        assert_eq!(ticks.len(), 1, "{}", name);
+        let found = &ticks[0];
+        let expected = tick("Binance", "SOL-DAI", 1_000, 1.0);
+        assert_eq!(found.symbol, expected.symbol, "{}", name);
+        assert_eq!(found.timestamp, expected.timestamp, "{}", name);
+        assert_eq!(
+            (found.size, found.buy_size, found.sell_size),
+            (1.0, 0.5, 0.25),
+            "{}",
+            name
+        );
+    }
+
+    async fn rollups_replace_and_delete_by_tier(name: &str, store: &dyn TickStore) {
+        let second = Resolution::from_millis(1_000).unwrap();
+        store
+            .insert_ticks(&[tick("Binance", "SOL-USDT", 500, 1.0)])
+            .await
+            .unwrap();
+        store
+            .insert_rollups(second, &[tick("Binance", "SOL-USDT", 0, 1.0)])
+            .await
+            .unwrap();
+        store
+            .insert_rollups(
+                second,
+                &[
+                    tick("Binance", "SOL-USDT", 0, 2.0),
+                    tick("Binance", "SOL-USDT", 1_000, 3.0),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let rollups = TickQuery::new()
+            .with_resolution(Some(second))
+            .with_order(SortOrder::Asc);
+        let prices: Vec<_> = store
+            .query_ticks(&rollups)
+            .await
+            .unwrap()
+            .ticks
+            .iter()
+            .map(|tick| tick.price)
+            .collect();
+        assert_eq!(prices, [2.0, 3.0], "{}", name);
+
+        assert_eq!(
+            store.delete_before(Some(second), at(1_000)).await.unwrap(),
+            1
+        );
+        assert_eq!(store.query_ticks(&rollups).await.unwrap().ticks.len(), 1);
+        assert_eq!(
+            store.delete_before(None, at(1_000)).await.unwrap(),
+            1,
+            "{}",
+            name
+        );
+    }
+
+    async fn candles_round_trip_by_resolution(name: &str, store: &dyn TickStore) {
+        let second = Resolution::from_millis(1_000).unwrap();
+        let minute = Resolution::from_millis(60_000).unwrap();
+        let candle = |resolution, millis| Candle {
+            exchange: Exchange::new("Binance"),
+            symbol: Pair::new("SOL", "DAI"),
+            resolution,
+            timestamp: at(millis),
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: 3.0,
+            trade_count: 2,
+            vwap: 1.25,
+        };
+        store
+            .insert_candles(&[candle(second, 1_000), candle(minute, 0), candle(second, 0)])
+            .await
+            .unwrap();
+
+        let candles = store
+            .query_candles(&CandleQuery::new(second).with_to(at(1_000)))
+            .await
+            .unwrap();
+        assert_eq!(candles.len(), 1, "{}", name);
+        assert_eq!(candles[0].timestamp, at(0));
+        assert_eq!(candles[0].symbol, Pair::new("SOL", "DAI"));
+        assert_eq!((candles[0].volume, candles[0].trade_count), (3.0, 2));
+    }
+
+    #[tokio::test]
+    async fn migrate_dashes_concatenated_symbols() {
+        let store = sqlite_store().await;
+        let insert = |symbol: &'static str| {
+            sqlx::query("INSERT INTO price_ticks (exchange, symbol, price, sz, buy_sz, sell_sz, ts) VALUES ('Binance', ?, 1.0, 1.0, 0.0, 0.0, 0)")
+                .bind(symbol)
+                .execute(&store.db)
+        };
+
+        insert("BTCFDUSD").await.unwrap();
+        insert("SOL-DAI").await.unwrap();
+        store.migrate().await.unwrap();
+        let symbols: Vec<String> =
+            sqlx::query_scalar("SELECT symbol FROM price_ticks ORDER BY symbol")
+                .fetch_all(&store.db)
+                .await
+                .unwrap();
+        assert_eq!(symbols, ["BTC-FDUSD", "SOL-DAI"]);
+
+        insert("SOLDAI").await.unwrap();
+        let err = store.migrate().await.unwrap_err();
+        assert!(err.to_string().contains("SOLDAI"), "{}", err);
+    }
 }
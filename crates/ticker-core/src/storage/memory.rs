@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        RwLock,
+        atomic::{AtomicI64, Ordering},
+    },
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::TickerError,
+    storage::{CandleQuery, SortOrder, TickPage, TickQuery, TickStore},
+    types::{Candle, PriceTick, Resolution, Trade},
+};
+
+/// `(id, tick)` rows in insertion order.
+type TickRows = Vec<(i64, PriceTick)>;
+
+/// Keeps everything in process memory. Intended for tests and demos.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// Raw ticks under `None`, rollups under their resolution.
+    ticks: RwLock<HashMap<Option<Resolution>, TickRows>>,
+    next_id: AtomicI64,
+    candles: RwLock<Vec<Candle>>,
+    trades: RwLock<Vec<Trade>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn candles(&self) -> Vec<Candle> {
+        self.candles.read().unwrap().clone()
+    }
+
+    pub fn trades(&self) -> Vec<Trade> {
+        self.trades.read().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl TickStore for MemoryStore {
+    async fn migrate(&self) -> Result<(), TickerError> {
+        Ok(())
+    }
+
+    async fn insert_ticks(&self, ticks: &[PriceTick]) -> Result<(), TickerError> {
+        let mut tables = self.ticks.write().unwrap();
+        let rows = tables.entry(None).or_default();
+        for tick in ticks {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            rows.push((id, tick.clone()));
+        }
+        Ok(())
+    }
+
+    async fn insert_candles(&self, candles: &[Candle]) -> Result<(), TickerError> {
+        self.candles.write().unwrap().extend_from_slice(candles);
+        Ok(())
+    }
+
+    async fn insert_trades(&self, trades: &[Trade]) -> Result<(), TickerError> {
+        self.trades.write().unwrap().extend_from_slice(trades);
+        Ok(())
+    }
+
+    async fn query_ticks(&self, query: &TickQuery) -> Result<TickPage, TickerError> {
+        let mut rows: Vec<_> = self
+            .ticks
+            .read()
+            .unwrap()
+            .get(&query.resolution)
+            .into_iter()
+            .flatten()
+            .filter(|(id, tick)| query.matches(*id, tick))
+            .cloned()
+            .collect();
+        rows.sort_by_key(|(id, tick)| (tick.timestamp, *id));
+        if query.order == SortOrder::Desc {
+            rows.reverse();
+        }
+        if let Some(limit) = query.limit {
+            rows.truncate(limit.saturating_add(1));
+        }
+        Ok(query.page(rows))
+    }
+
+    async fn query_candles(&self, query: &CandleQuery) -> Result<Vec<Candle>, TickerError> {
+        let mut candles: Vec<_> = self
+            .candles
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|candle| query.matches(candle))
+            .cloned()
+            .collect();
+        candles.sort_by_key(|candle| candle.timestamp);
+        Ok(candles)
+    }
+
+    async fn insert_rollups(
+        &self,
+        resolution: Resolution,
+        ticks: &[PriceTick],
+    ) -> Result<(), TickerError> {
+        let mut tables = self.ticks.write().unwrap();
+        let rows = tables.entry(Some(resolution)).or_default();
+        let mut index: HashMap<_, _> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, (_, row))| ((row.exchange.clone(), row.symbol.clone(), row.timestamp), i))
+            .collect();
+        for tick in ticks {
+            let key = (tick.exchange.clone(), tick.symbol.clone(), tick.timestamp);
+            match index.get(&key) {
+                Some(&i) => rows[i].1 = tick.clone(),
+                None => {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+                    index.insert(key, rows.len());
+                    rows.push((id, tick.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_before(
+        &self,
+        resolution: Option<Resolution>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, TickerError> {
+        let mut tables = self.ticks.write().unwrap();
+        let Some(rows) = tables.get_mut(&resolution) else {
+            return Ok(0);
+        };
+        let len = rows.len();
+        rows.retain(|(_, tick)| tick.timestamp >= before);
+        Ok((len - rows.len()) as u64)
+    }
+}
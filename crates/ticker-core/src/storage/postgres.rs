@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+use crate::{
+    error::TickerError,
+    storage::{
+        CandleQuery, MAX_ROWS_PER_INSERT, ROLLUP_UPSERT, SYMBOL_TABLES, TickPage, TickQuery,
+        TickStore, dashed_symbols,
+    },
+    types::{Candle, CandleRecord, PriceTick, PriceTickRecord, Resolution, Trade},
+};
+
+type PgTickRow = (i64, String, String, f64, f64, f64, f64, DateTime<Utc>);
+type PgCandleRow = (
+    String,
+    String,
+    i64,
+    DateTime<Utc>,
+    f64,
+    f64,
+    f64,
+    f64,
+    f64,
+    i64,
+    f64,
+);
+
+#[derive(Debug, Clone)]
+pub struct PgStore {
+    pub db: PgPool,
+}
+
+impl PgStore {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, TickerError> {
+        Ok(Self::new(PgPool::connect(url).await?))
+    }
+
+    /// Rewrites concatenated symbols as `BASE-QUOTE`, all tables in one
+    /// transaction.
+    async fn dash_symbols(&self) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+        for table in SYMBOL_TABLES {
+            let symbols: Vec<String> = sqlx::query_scalar(&format!(
+                "SELECT DISTINCT symbol FROM {} WHERE symbol NOT LIKE '%-%'",
+                table
+            ))
+            .fetch_all(&mut *tx)
+            .await?;
+            for (from, to) in dashed_symbols(symbols)? {
+                sqlx::query(&format!(
+                    "UPDATE {} SET symbol = $1 WHERE symbol = $2",
+                    table
+                ))
+                .bind(to)
+                .bind(from)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    fn decode_ticks(&self, rows: Vec<PgTickRow>) -> Result<Vec<(i64, PriceTick)>, TickerError> {
+        rows.into_iter()
+            .map(|(id, exchange, symbol, price, sz, buy_sz, sell_sz, ts)| {
+                let record = PriceTickRecord {
+                    exchange,
+                    symbol,
+                    price,
+                    sz,
+                    buy_sz,
+                    sell_sz,
+                    ts: ts.timestamp_micros(),
+                };
+                Ok((id, PriceTick::try_from_db_record(record)?))
+            })
+            .collect()
+    }
+
+    fn decode_candles(&self, rows: Vec<PgCandleRow>) -> Result<Vec<Candle>, TickerError> {
+        rows.into_iter()
+            .map(
+                |(
+                    exchange,
+                    symbol,
+                    resolution_ms,
+                    ts,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    trade_count,
+                    vwap,
+                )| {
+                    let record = CandleRecord {
+                        exchange,
+                        symbol,
+                        resolution_ms,
+                        ts: ts.timestamp_micros(),
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        trade_count,
+                        vwap,
+                    };
+                    Candle::try_from_db_record(record)
+                },
+            )
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl TickStore for PgStore {
+    async fn migrate(&self) -> Result<(), TickerError> {
+        sqlx::migrate!("../../migrations/postgres")
+            .run(&self.db)
+            .await?;
+        self.dash_symbols().await
+    }
+
+    async fn insert_ticks(&self, ticks: &[PriceTick]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in ticks.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "INSERT INTO price_ticks (exchange, symbol, price, sz, buy_sz, sell_sz, ts) ",
+            );
+            query.push_values(chunk, |mut row, tick| {
+                row.push_bind(tick.exchange.to_string())
+                    .push_bind(tick.symbol.to_string())
+                    .push_bind(tick.price)
+                    .push_bind(tick.size)
+                    .push_bind(tick.buy_size)
+                    .push_bind(tick.sell_size)
+                    .push_bind(tick.timestamp);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_candles(&self, candles: &[Candle]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in candles.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "INSERT INTO candles (exchange, symbol, resolution_ms, ts, open, high, low, close, volume, trade_count, vwap) ",
+            );
+            query.push_values(chunk, |mut row, candle| {
+                row.push_bind(candle.exchange.to_string())
+                    .push_bind(candle.symbol.to_string())
+                    .push_bind(candle.resolution.as_millis())
+                    .push_bind(candle.timestamp)
+                    .push_bind(candle.open)
+                    .push_bind(candle.high)
+                    .push_bind(candle.low)
+                    .push_bind(candle.close)
+                    .push_bind(candle.volume)
+                    .push_bind(candle.trade_count)
+                    .push_bind(candle.vwap);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_trades(&self, trades: &[Trade]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in trades.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "INSERT INTO trades (exchange, symbol, price, sz, side, trade_id, seq, ts, received_ts) ",
+            );
+            query.push_values(chunk, |mut row, trade| {
+                row.push_bind(trade.exchange.to_string())
+                    .push_bind(trade.symbol.to_string())
+                    .push_bind(trade.tick.price)
+                    .push_bind(trade.tick.size)
+                    .push_bind(trade.tick.side.map(|side| side.to_string()))
+                    .push_bind(trade.tick.trade_id.clone())
+                    .push_bind(trade.tick.sequence.map(|seq| seq as i64))
+                    .push_bind(trade.tick.timestamp)
+                    .push_bind(trade.tick.received_at);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn query_ticks(&self, query: &TickQuery) -> Result<TickPage, TickerError> {
+        let mut builder = QueryBuilder::<Postgres>::new("");
+        query.push_select(&mut builder, |ts| ts);
+        let rows = builder
+            .build_query_as::<PgTickRow>()
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(query.page(self.decode_ticks(rows)?))
+    }
+
+    async fn query_candles(&self, query: &CandleQuery) -> Result<Vec<Candle>, TickerError> {
+        let mut builder = QueryBuilder::<Postgres>::new("");
+        query.push_select(&mut builder, |ts| ts);
+        let rows = builder
+            .build_query_as::<PgCandleRow>()
+            .fetch_all(&self.db)
+            .await?;
+
+        self.decode_candles(rows)
+    }
+
+    async fn insert_rollups(
+        &self,
+        resolution: Resolution,
+        ticks: &[PriceTick],
+    ) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in ticks.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "INSERT INTO price_tick_rollups (exchange, symbol, resolution_ms, price, sz, buy_sz, sell_sz, ts) ",
+            );
+            query.push_values(chunk, |mut row, tick| {
+                row.push_bind(tick.exchange.to_string())
+                    .push_bind(tick.symbol.to_string())
+                    .push_bind(resolution.as_millis())
+                    .push_bind(tick.price)
+                    .push_bind(tick.size)
+                    .push_bind(tick.buy_size)
+                    .push_bind(tick.sell_size)
+                    .push_bind(tick.timestamp);
+            });
+            query.push(ROLLUP_UPSERT);
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_before(
+        &self,
+        resolution: Option<Resolution>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, TickerError> {
+        let result = match resolution {
+            None => {
+                sqlx::query("DELETE FROM price_ticks WHERE ts < $1")
+                    .bind(before)
+                    .execute(&self.db)
+                    .await?
+            }
+            Some(resolution) => {
+                sqlx::query("DELETE FROM price_tick_rollups WHERE resolution_ms = $1 AND ts < $2")
+                    .bind(resolution.as_millis())
+                    .bind(before)
+                    .execute(&self.db)
+                    .await?
+            }
+        };
+        Ok(result.rows_affected())
+    }
+}
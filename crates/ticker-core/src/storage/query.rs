@@ -0,0 +1,361 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use sqlx::{Database, Encode, QueryBuilder, Type};
+
+use crate::{
+    error::TickerError,
+    types::{Candle, Exchange, Pair, PriceTick, Resolution},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Position just past the last tick of a page. Ticks are ordered by
+/// `(timestamp, id)` so the cursor stays stable while new rows arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: i64,
+}
+
+/// Filters for [`TickStore::query_ticks`](crate::storage::TickStore::query_ticks).
+/// Empty exchange/pair sets match everything; the time range is `[from, to)`.
+/// Without a resolution the raw ticks are read, otherwise the rollups of that
+/// width written by the retention task.
+#[derive(Debug, Clone, Default)]
+pub struct TickQuery {
+    pub resolution: Option<Resolution>,
+    pub exchanges: Vec<Exchange>,
+    pub pairs: Vec<Pair>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub order: SortOrder,
+    pub limit: Option<usize>,
+    pub cursor: Option<TickCursor>,
+}
+
+/// Filters for [`TickStore::query_candles`](crate::storage::TickStore::query_candles),
+/// matched like [`TickQuery`]. Bars are returned oldest first.
+#[derive(Debug, Clone)]
+pub struct CandleQuery {
+    pub resolution: Resolution,
+    pub exchanges: Vec<Exchange>,
+    pub pairs: Vec<Pair>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TickPage {
+    pub ticks: Vec<PriceTick>,
+    /// Set when more ticks match; pass it back via [`TickQuery::with_cursor`].
+    pub next_cursor: Option<TickCursor>,
+}
+
+impl TickQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_resolution(mut self, resolution: Option<Resolution>) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_exchanges(mut self, exchanges: impl IntoIterator<Item = Exchange>) -> Self {
+        self.exchanges.extend(exchanges);
+        self
+    }
+
+    pub fn with_pairs(mut self, pairs: impl IntoIterator<Item = Pair>) -> Self {
+        self.pairs.extend(pairs);
+        self
+    }
+
+    pub fn with_from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn with_to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub fn with_order(mut self, order: SortOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: TickCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    pub(crate) fn matches(&self, id: i64, tick: &PriceTick) -> bool {
+        (self.exchanges.is_empty() || self.exchanges.contains(&tick.exchange))
+            && (self.pairs.is_empty() || self.pairs.contains(&tick.symbol))
+            && self.from.is_none_or(|from| tick.timestamp >= from)
+            && self.to.is_none_or(|to| tick.timestamp < to)
+            && self.cursor.is_none_or(|cursor| {
+                let key = (tick.timestamp, id);
+                let after = (cursor.timestamp, cursor.id);
+                match self.order {
+                    SortOrder::Asc => key > after,
+                    SortOrder::Desc => key < after,
+                }
+            })
+    }
+
+    /// Builds the select for this query. `encode_ts` maps a timestamp to the
+    /// backend's `ts` encoding. One extra row is requested so
+    /// [`TickQuery::page`] can tell whether another page follows.
+    pub(crate) fn push_select<'args, DB, T>(
+        &self,
+        builder: &mut QueryBuilder<'args, DB>,
+        encode_ts: impl Fn(DateTime<Utc>) -> T,
+    ) where
+        DB: Database,
+        String: Encode<'args, DB> + Type<DB>,
+        i64: Encode<'args, DB> + Type<DB>,
+        T: 'args + Encode<'args, DB> + Type<DB>,
+    {
+        builder.push("SELECT id, exchange, symbol, price, sz, buy_sz, sell_sz, ts FROM ");
+        let mut keyword = " WHERE ";
+        let mut next = |builder: &mut QueryBuilder<'args, DB>| {
+            builder.push(keyword);
+            keyword = " AND ";
+        };
+
+        match self.resolution {
+            None => {
+                builder.push("price_ticks");
+            }
+            Some(resolution) => {
+                builder.push("price_tick_rollups");
+                next(builder);
+                builder
+                    .push("resolution_ms = ")
+                    .push_bind(resolution.as_millis());
+            }
+        }
+
+        push_filters(
+            builder,
+            &mut next,
+            &self.exchanges,
+            &self.pairs,
+            self.from,
+            self.to,
+            &encode_ts,
+        );
+        if let Some(cursor) = self.cursor {
+            next(builder);
+            builder
+                .push(match self.order {
+                    SortOrder::Asc => "(ts, id) > (",
+                    SortOrder::Desc => "(ts, id) < (",
+                })
+                .push_bind(encode_ts(cursor.timestamp))
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+
+        builder.push(match self.order {
+            SortOrder::Asc => " ORDER BY ts ASC, id ASC",
+            SortOrder::Desc => " ORDER BY ts DESC, id DESC",
+        });
+        if let Some(limit) = self.limit {
+            builder
+                .push(" LIMIT ")
+                .push_bind(limit.saturating_add(1) as i64);
+        }
+    }
+
+    /// Turns ordered `(id, tick)` rows, including the extra look-ahead row,
+    /// into a page.
+    pub(crate) fn page(&self, mut rows: Vec<(i64, PriceTick)>) -> TickPage {
+        let mut next_cursor = None;
+        if let Some(limit) = self.limit
+            && rows.len() > limit
+        {
+            rows.truncate(limit);
+            next_cursor = rows.last().map(|(id, tick)| TickCursor {
+                timestamp: tick.timestamp,
+                id: *id,
+            });
+        }
+
+        TickPage {
+            ticks: rows.into_iter().map(|(_, tick)| tick).collect(),
+            next_cursor,
+        }
+    }
+}
+
+impl CandleQuery {
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            exchanges: Vec::new(),
+            pairs: Vec::new(),
+            from: None,
+            to: None,
+        }
+    }
+
+    pub fn with_exchanges(mut self, exchanges: impl IntoIterator<Item = Exchange>) -> Self {
+        self.exchanges.extend(exchanges);
+        self
+    }
+
+    pub fn with_pairs(mut self, pairs: impl IntoIterator<Item = Pair>) -> Self {
+        self.pairs.extend(pairs);
+        self
+    }
+
+    pub fn with_from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn with_to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    pub(crate) fn matches(&self, candle: &Candle) -> bool {
+        candle.resolution == self.resolution
+            && (self.exchanges.is_empty() || self.exchanges.contains(&candle.exchange))
+            && (self.pairs.is_empty() || self.pairs.contains(&candle.symbol))
+            && self.from.is_none_or(|from| candle.timestamp >= from)
+            && self.to.is_none_or(|to| candle.timestamp < to)
+    }
+
+    /// Builds the select for this query; see [`TickQuery::push_select`].
+    pub(crate) fn push_select<'args, DB, T>(
+        &self,
+        builder: &mut QueryBuilder<'args, DB>,
+        encode_ts: impl Fn(DateTime<Utc>) -> T,
+    ) where
+        DB: Database,
+        String: Encode<'args, DB> + Type<DB>,
+        i64: Encode<'args, DB> + Type<DB>,
+        T: 'args + Encode<'args, DB> + Type<DB>,
+    {
+        builder
+            .push("SELECT exchange, symbol, resolution_ms, ts, open, high, low, close, volume, trade_count, vwap FROM candles WHERE resolution_ms = ")
+            .push_bind(self.resolution.as_millis());
+        let mut next = |builder: &mut QueryBuilder<'args, DB>| {
+            builder.push(" AND ");
+        };
+        push_filters(
+            builder,
+            &mut next,
+            &self.exchanges,
+            &self.pairs,
+            self.from,
+            self.to,
+            &encode_ts,
+        );
+        builder.push(" ORDER BY ts ASC, id ASC");
+    }
+}
+
+/// Pushes the exchange, pair and `[from, to)` conditions shared by the tick
+/// and candle selects, calling `next` before each one.
+fn push_filters<'args, DB, T>(
+    builder: &mut QueryBuilder<'args, DB>,
+    next: &mut impl FnMut(&mut QueryBuilder<'args, DB>),
+    exchanges: &[Exchange],
+    pairs: &[Pair],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    encode_ts: &impl Fn(DateTime<Utc>) -> T,
+) where
+    DB: Database,
+    String: Encode<'args, DB> + Type<DB>,
+    T: 'args + Encode<'args, DB> + Type<DB>,
+{
+    if !exchanges.is_empty() {
+        next(builder);
+        builder.push("exchange IN (");
+        let mut list = builder.separated(", ");
+        for exchange in exchanges {
+            list.push_bind(exchange.to_string());
+        }
+        builder.push(")");
+    }
+    if !pairs.is_empty() {
+        next(builder);
+        builder.push("symbol IN (");
+        let mut list = builder.separated(", ");
+        for pair in pairs {
+            list.push_bind(pair.to_string());
+        }
+        builder.push(")");
+    }
+    if let Some(from) = from {
+        next(builder);
+        builder.push("ts >= ").push_bind(encode_ts(from));
+    }
+    if let Some(to) = to {
+        next(builder);
+        builder.push("ts < ").push_bind(encode_ts(to));
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = TickerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(TickerError::InvalidArgument(format!(
+                "Invalid sort order: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl FromStr for TickCursor {
+    type Err = TickerError;
+
+    /// Parses the `<epoch micros>_<id>` form produced by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TickerError::InvalidArgument(format!("Invalid cursor: {}", s));
+        let (micros, id) = s.split_once('_').ok_or_else(invalid)?;
+        let micros: i64 = micros.parse().map_err(|_| invalid())?;
+
+        Ok(Self {
+            timestamp: DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl std::fmt::Display for TickCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.timestamp.timestamp_micros(), self.id)
+    }
+}
+
+impl Serialize for TickCursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
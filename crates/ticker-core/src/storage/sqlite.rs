@@ -0,0 +1,267 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, sqlite::SqliteConnectOptions};
+
+use crate::{
+    error::TickerError,
+    storage::{
+        CandleQuery, MAX_ROWS_PER_INSERT, ROLLUP_UPSERT, SYMBOL_TABLES, TickPage, TickQuery,
+        TickStore, dashed_symbols,
+    },
+    types::{Candle, CandleRecord, PriceTick, PriceTickRecord, Resolution, Trade},
+};
+
+type SqliteTickRow = (i64, String, String, f64, f64, f64, f64, i64);
+type SqliteCandleRow = (String, String, i64, i64, f64, f64, f64, f64, f64, i64, f64);
+
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pub db: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    pub async fn connect(url: &str) -> Result<Self, TickerError> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        Ok(Self::new(SqlitePool::connect_with(options).await?))
+    }
+
+    /// Rewrites concatenated symbols as `BASE-QUOTE`, all tables in one
+    /// transaction.
+    async fn dash_symbols(&self) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+        for table in SYMBOL_TABLES {
+            let symbols: Vec<String> = sqlx::query_scalar(&format!(
+                "SELECT DISTINCT symbol FROM {} WHERE symbol NOT LIKE '%-%'",
+                table
+            ))
+            .fetch_all(&mut *tx)
+            .await?;
+            for (from, to) in dashed_symbols(symbols)? {
+                sqlx::query(&format!("UPDATE {} SET symbol = ? WHERE symbol = ?", table))
+                    .bind(to)
+                    .bind(from)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    fn decode_ticks(&self, rows: Vec<SqliteTickRow>) -> Result<Vec<(i64, PriceTick)>, TickerError> {
+        rows.into_iter()
+            .map(|(id, exchange, symbol, price, sz, buy_sz, sell_sz, ts)| {
+                let record = PriceTickRecord {
+                    exchange,
+                    symbol,
+                    price,
+                    sz,
+                    buy_sz,
+                    sell_sz,
+                    ts,
+                };
+                Ok((id, PriceTick::try_from_db_record(record)?))
+            })
+            .collect()
+    }
+
+    fn decode_candles(&self, rows: Vec<SqliteCandleRow>) -> Result<Vec<Candle>, TickerError> {
+        rows.into_iter()
+            .map(
+                |(
+                    exchange,
+                    symbol,
+                    resolution_ms,
+                    ts,
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    trade_count,
+                    vwap,
+                )| {
+                    let record = CandleRecord {
+                        exchange,
+                        symbol,
+                        resolution_ms,
+                        ts,
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        trade_count,
+                        vwap,
+                    };
+                    Candle::try_from_db_record(record)
+                },
+            )
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl TickStore for SqliteStore {
+    async fn migrate(&self) -> Result<(), TickerError> {
+        sqlx::migrate!("../../migrations").run(&self.db).await?;
+        self.dash_symbols().await
+    }
+
+    async fn insert_ticks(&self, ticks: &[PriceTick]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in ticks.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO price_ticks (exchange, symbol, price, sz, buy_sz, sell_sz, ts) ",
+            );
+            query.push_values(chunk, |mut row, tick| {
+                row.push_bind(tick.exchange.to_string())
+                    .push_bind(tick.symbol.to_string())
+                    .push_bind(tick.price)
+                    .push_bind(tick.size)
+                    .push_bind(tick.buy_size)
+                    .push_bind(tick.sell_size)
+                    .push_bind(tick.timestamp.timestamp_micros());
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_candles(&self, candles: &[Candle]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in candles.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO candles (exchange, symbol, resolution_ms, ts, open, high, low, close, volume, trade_count, vwap) ",
+            );
+            query.push_values(chunk, |mut row, candle| {
+                row.push_bind(candle.exchange.to_string())
+                    .push_bind(candle.symbol.to_string())
+                    .push_bind(candle.resolution.as_millis())
+                    .push_bind(candle.timestamp.timestamp_micros())
+                    .push_bind(candle.open)
+                    .push_bind(candle.high)
+                    .push_bind(candle.low)
+                    .push_bind(candle.close)
+                    .push_bind(candle.volume)
+                    .push_bind(candle.trade_count)
+                    .push_bind(candle.vwap);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_trades(&self, trades: &[Trade]) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in trades.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO trades (exchange, symbol, price, sz, side, trade_id, seq, ts, received_ts) ",
+            );
+            query.push_values(chunk, |mut row, trade| {
+                row.push_bind(trade.exchange.to_string())
+                    .push_bind(trade.symbol.to_string())
+                    .push_bind(trade.tick.price)
+                    .push_bind(trade.tick.size)
+                    .push_bind(trade.tick.side.map(|side| side.to_string()))
+                    .push_bind(trade.tick.trade_id.clone())
+                    .push_bind(trade.tick.sequence.map(|seq| seq as i64))
+                    .push_bind(trade.tick.timestamp.timestamp_micros())
+                    .push_bind(trade.tick.received_at.timestamp_micros());
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn query_ticks(&self, query: &TickQuery) -> Result<TickPage, TickerError> {
+        let mut builder = QueryBuilder::<Sqlite>::new("");
+        query.push_select(&mut builder, |ts| ts.timestamp_micros());
+        let rows = builder
+            .build_query_as::<SqliteTickRow>()
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(query.page(self.decode_ticks(rows)?))
+    }
+
+    async fn query_candles(&self, query: &CandleQuery) -> Result<Vec<Candle>, TickerError> {
+        let mut builder = QueryBuilder::<Sqlite>::new("");
+        query.push_select(&mut builder, |ts| ts.timestamp_micros());
+        let rows = builder
+            .build_query_as::<SqliteCandleRow>()
+            .fetch_all(&self.db)
+            .await?;
+
+        self.decode_candles(rows)
+    }
+
+    async fn insert_rollups(
+        &self,
+        resolution: Resolution,
+        ticks: &[PriceTick],
+    ) -> Result<(), TickerError> {
+        let mut tx = self.db.begin().await?;
+
+        for chunk in ticks.chunks(MAX_ROWS_PER_INSERT) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO price_tick_rollups (exchange, symbol, resolution_ms, price, sz, buy_sz, sell_sz, ts) ",
+            );
+            query.push_values(chunk, |mut row, tick| {
+                row.push_bind(tick.exchange.to_string())
+                    .push_bind(tick.symbol.to_string())
+                    .push_bind(resolution.as_millis())
+                    .push_bind(tick.price)
+                    .push_bind(tick.size)
+                    .push_bind(tick.buy_size)
+                    .push_bind(tick.sell_size)
+                    .push_bind(tick.timestamp.timestamp_micros());
+            });
+            query.push(ROLLUP_UPSERT);
+            query.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_before(
+        &self,
+        resolution: Option<Resolution>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, TickerError> {
+        let before = before.timestamp_micros();
+        let result = match resolution {
+            None => {
+                sqlx::query_file!("queries/delete_price_ticks_before.sql", before)
+                    .execute(&self.db)
+                    .await?
+            }
+            Some(resolution) => {
+                let resolution_ms = resolution.as_millis();
+                sqlx::query_file!(
+                    "queries/delete_price_tick_rollups_before.sql",
+                    resolution_ms,
+                    before
+                )
+                .execute(&self.db)
+                .await?
+            }
+        };
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{
+    sync::watch,
+    task::{JoinError, JoinHandle},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::TickerError;
+
+/// Whether a supervised task is started again once it returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RestartPolicy {
+    Never,
+    /// Restart whenever the task exits, whether it failed or not.
+    Always,
+    /// Restart after an error or panic, giving up after `max_retries`
+    /// consecutive failures.
+    OnFailure {
+        max_retries: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// Delay before the first restart, doubled after each consecutive failure.
+    pub restart_delay: Duration,
+    pub max_restart_delay: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TaskStatus {
+    Running,
+    Restarting,
+    /// Cancelled through its handle.
+    Stopped,
+    /// Returned without error and was not restarted.
+    Finished,
+    /// Failed and was not restarted.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskState {
+    pub name: String,
+    pub status: TaskStatus,
+    pub since: DateTime<Utc>,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+pub struct TaskHandle {
+    pub state: watch::Receiver<TaskState>,
+    pub cancel: CancellationToken,
+    pub task: JoinHandle<()>,
+    id: u64,
+    tasks: Tasks,
+}
+
+/// States of the live tasks by spawn order.
+type Tasks = Arc<Mutex<BTreeMap<u64, watch::Receiver<TaskState>>>>;
+
+/// Runs tasks under a restart policy and keeps track of their state.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    pub config: SupervisorConfig,
+    tasks: Tasks,
+    next_id: AtomicU64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::OnFailure { max_retries: 5 }
+    }
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            restart_delay: Duration::from_secs(1),
+            max_restart_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    fn backoff(&self, failures: u32) -> Duration {
+        let exp = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.restart_delay
+            .saturating_mul(exp)
+            .min(self.max_restart_delay)
+    }
+}
+
+impl TaskState {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            status: TaskStatus::Running,
+            since: Utc::now(),
+            restarts: 0,
+            last_error: None,
+            last_error_at: None,
+        }
+    }
+
+    fn set_status(&mut self, status: TaskStatus) {
+        self.status = status;
+        self.since = Utc::now();
+    }
+}
+
+impl TaskHandle {
+    pub fn status(&self) -> TaskStatus {
+        self.state.borrow().status
+    }
+
+    /// Cancels the task, keeps it from being restarted and drops it from
+    /// [`Supervisor::states`].
+    pub fn stop(&self) {
+        self.cancel.cancel();
+        self.tasks.lock().unwrap().remove(&self.id);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Cancels the task and waits for its current run to return.
+    pub async fn shutdown(self) {
+        self.stop();
+        let _ = self.task.await;
+    }
+}
+
+impl Supervisor {
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            tasks: Tasks::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Spawns the future built by `make` and builds a new one whenever the
+    /// policy asks for a restart. Failures count as consecutive unless the
+    /// run before them lasted longer than `max_restart_delay`. The task is
+    /// listed by [`Supervisor::states`] until it stops, finishes or fails.
+    pub fn spawn<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        policy: RestartPolicy,
+        mut make: F,
+    ) -> TaskHandle
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), TickerError>> + Send + 'static,
+    {
+        let name = name.into();
+        let (state_tx, state) = watch::channel(TaskState::new(name.clone()));
+        let cancel = CancellationToken::new();
+        let config = self.config.clone();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // Listed before the task starts, so it can never unlist itself first.
+        self.tasks.lock().unwrap().insert(id, state.clone());
+
+        let task = tokio::spawn({
+            let (cancel, tasks) = (cancel.clone(), self.tasks.clone());
+            async move {
+                let mut failures = 0;
+                loop {
+                    let started = Instant::now();
+                    let result = match tokio::spawn(make(cancel.clone())).await {
+                        Ok(result) => result.map_err(|e| e.to_string()),
+                        Err(e) => Err(panic_message(e)),
+                    };
+                    if started.elapsed() > config.max_restart_delay {
+                        failures = 0;
+                    }
+                    if let Err(error) = &result {
+                        eprintln!("Task {} failed: {}", name, error);
+                        failures += 1;
+                        state_tx.send_modify(|state| {
+                            state.last_error = Some(error.clone());
+                            state.last_error_at = Some(Utc::now());
+                        });
+                    }
+                    if cancel.is_cancelled() {
+                        state_tx.send_modify(|state| state.set_status(TaskStatus::Stopped));
+                        break;
+                    }
+
+                    let restart = match policy {
+                        RestartPolicy::Never => false,
+                        RestartPolicy::Always => true,
+                        RestartPolicy::OnFailure { max_retries } => {
+                            result.is_err() && failures <= max_retries
+                        }
+                    };
+                    if !restart {
+                        let status = match result {
+                            Ok(()) => TaskStatus::Finished,
+                            Err(_) => TaskStatus::Failed,
+                        };
+                        if status == TaskStatus::Failed {
+                            eprintln!("Task {} failed {} times, giving up", name, failures);
+                        }
+                        state_tx.send_modify(|state| state.set_status(status));
+                        break;
+                    }
+
+                    let delay = config.backoff(failures);
+                    println!("Restarting task {} in {:?}", name, delay);
+                    state_tx.send_modify(|state| {
+                        state.set_status(TaskStatus::Restarting);
+                        state.restarts += 1;
+                    });
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = cancel.cancelled() => {
+                            state_tx.send_modify(|state| state.set_status(TaskStatus::Stopped));
+                            break;
+                        }
+                    }
+                    state_tx.send_modify(|state| state.set_status(TaskStatus::Running));
+                }
+                tasks.lock().unwrap().remove(&id);
+            }
+        });
+
+        TaskHandle {
+            state,
+            cancel,
+            task,
+            id,
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    /// State of every task still running or waiting to restart, in spawn
+    /// order.
+    pub fn states(&self) -> Vec<TaskState> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .values()
+            .map(|state| state.borrow().clone())
+            .collect()
+    }
+}
+
+fn panic_message(err: JoinError) -> String {
+    if !err.is_panic() {
+        return err.to_string();
+    }
+    let payload = err.into_panic();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    format!("panicked: {}", message)
+}
+
+impl FromStr for RestartPolicy {
+    type Err = TickerError;
+
+    /// Parses `never`, `always`, or `on-failure:<max retries>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TickerError::InvalidArgument(format!("Invalid restart policy: {}", s));
+        match s.trim().to_lowercase().as_str() {
+            "never" => Ok(RestartPolicy::Never),
+            "always" => Ok(RestartPolicy::Always),
+            policy => match policy.split_once(':') {
+                Some(("on-failure", max_retries)) => Ok(RestartPolicy::OnFailure {
+                    max_retries: max_retries.parse().map_err(|_| invalid())?,
+                }),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestartPolicy::Never => write!(f, "never"),
+            RestartPolicy::Always => write!(f, "always"),
+            RestartPolicy::OnFailure { max_retries } => write!(f, "on-failure:{}", max_retries),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tasks_are_unlisted_once_they_end() {
+        let supervisor = Supervisor::default();
+        let finished = supervisor.spawn("finished", RestartPolicy::Never, |_| async { Ok(()) });
+        let failed = supervisor.spawn("failed", RestartPolicy::Never, |_| async {
+            Err(TickerError::ChannelClosed)
+        });
+        let running = supervisor.spawn("running", RestartPolicy::Never, |cancel| async move {
+            cancel.cancelled().await;
+            Ok(())
+        });
+
+        let _ = finished.task.await;
+        let _ = failed.task.await;
+        let names: Vec<_> = supervisor.states().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["running"]);
+
+        running.stop();
+        assert!(supervisor.states().is_empty());
+    }
+}
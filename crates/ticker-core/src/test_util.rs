@@ -0,0 +1,35 @@
+//! Fixtures shared by the unit tests.
+
+use chrono::{DateTime, Utc};
+
+use crate::types::{Exchange, PriceTick, RawPriceTick};
+
+pub(crate) fn at(millis: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(millis).unwrap()
+}
+
+/// A trade received the moment it happened, without side or ids.
+pub(crate) fn trade(millis: i64, price: f64, size: f64) -> RawPriceTick {
+    RawPriceTick {
+        price,
+        size,
+        timestamp: at(millis),
+        received_at: at(millis),
+        side: None,
+        trade_id: None,
+        sequence: None,
+    }
+}
+
+/// A tick of one unit, half of it bought and a quarter sold.
+pub(crate) fn tick(exchange: &str, pair: &str, millis: i64, price: f64) -> PriceTick {
+    PriceTick {
+        exchange: Exchange::new(exchange),
+        symbol: pair.parse().unwrap(),
+        price,
+        size: 1.0,
+        buy_size: 0.5,
+        sell_size: 0.25,
+        timestamp: at(millis),
+    }
+}
@@ -1,19 +1,16 @@
-use std::pin::Pin;
+use std::{pin::Pin, str::FromStr, time::Duration};
 
-use chrono::{DateTime, NaiveDateTime, Utc};
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
 use tokio_stream::Stream;
 
 use crate::error::TickerError;
 
 pub type EventStream<'a, E> = Pin<Box<dyn Stream<Item = E> + Send + 'a>>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
-pub enum Exchange {
-    Binance,
-    Bybit,
-    Coinbase,
-}
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Exchange(String);
 
 #[derive(Debug)]
 pub enum Event {
@@ -22,13 +19,13 @@ pub enum Event {
     Unsupported,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
-pub enum Pair {
-    BTCUSDT,
-    ETHUSDT,
-    SOLUSDT,
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pair {
+    pub base: String,
+    pub quote: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum PairFormat {
     Upper,
     Lower,
@@ -36,63 +33,139 @@ pub enum PairFormat {
     LowerWithDash,
 }
 
-#[derive(Debug)]
+/// Aggressor side of a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone)]
 pub struct RawPriceTick {
     pub price: f64,
     pub size: f64,
     pub timestamp: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+    pub side: Option<Side>,
+    pub trade_id: Option<String>,
+    pub sequence: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub exchange: Exchange,
+    pub symbol: Pair,
+    pub tick: RawPriceTick,
+}
+
+/// Width of an aggregation bucket, parsed from strings such as `100ms`, `1s`,
+/// `5m`, `1h` or `1d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Resolution {
+    millis: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candle {
+    pub exchange: Exchange,
+    pub symbol: Pair,
+    pub resolution: Resolution,
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trade_count: i64,
+    pub vwap: f64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PriceTick {
     pub exchange: Exchange,
     pub symbol: Pair,
     pub price: f64,
     pub size: f64,
+    pub buy_size: f64,
+    pub sell_size: f64,
     pub timestamp: DateTime<Utc>,
 }
 
-impl PriceTick {
-    pub fn try_from_db_record(
-        exchange: String,
-        symbol: String,
-        price: f64,
-        size: f64,
-        timestamp: NaiveDateTime,
-    ) -> Result<Self, TickerError> {
+#[derive(Debug)]
+pub struct PriceTickRecord {
+    pub exchange: String,
+    pub symbol: String,
+    pub price: f64,
+    pub sz: f64,
+    pub buy_sz: f64,
+    pub sell_sz: f64,
+    /// Microseconds since the Unix epoch.
+    pub ts: i64,
+}
+
+#[derive(Debug)]
+pub struct CandleRecord {
+    pub exchange: String,
+    pub symbol: String,
+    pub resolution_ms: i64,
+    /// Microseconds since the Unix epoch.
+    pub ts: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trade_count: i64,
+    pub vwap: f64,
+}
+
+impl Candle {
+    /// Takes the stored exchange id as is, so rows from exchanges that are no
+    /// longer registered still read back.
+    pub fn try_from_db_record(record: CandleRecord) -> Result<Self, TickerError> {
         Ok(Self {
-            exchange: exchange.try_into()?,
-            symbol: symbol.try_into()?,
-            price,
-            size,
-            timestamp: DateTime::<Utc>::from_naive_utc_and_offset(timestamp, Utc),
+            exchange: Exchange::new(record.exchange),
+            symbol: record.symbol.try_into()?,
+            resolution: Resolution::from_millis(record.resolution_ms)?,
+            timestamp: DateTime::from_timestamp_micros(record.ts).ok_or_else(|| {
+                TickerError::InvalidArgument(format!("Timestamp out of range: {}", record.ts))
+            })?,
+            open: record.open,
+            high: record.high,
+            low: record.low,
+            close: record.close,
+            volume: record.volume,
+            trade_count: record.trade_count,
+            vwap: record.vwap,
         })
     }
+}
 
-    pub fn into_strings(self) -> (String, String, String, String, String) {
-        (
-            self.exchange.to_string(),
-            self.symbol.to_string(),
-            self.price.to_string(),
-            self.size.to_string(),
-            self.timestamp.to_rfc3339(),
-        )
+impl PriceTick {
+    /// Takes the stored exchange id as is, so rows from exchanges that are no
+    /// longer registered still read back.
+    pub fn try_from_db_record(record: PriceTickRecord) -> Result<Self, TickerError> {
+        Ok(Self {
+            exchange: Exchange::new(record.exchange),
+            symbol: record.symbol.try_into()?,
+            price: record.price,
+            size: record.sz,
+            buy_size: record.buy_sz,
+            sell_size: record.sell_sz,
+            timestamp: DateTime::from_timestamp_micros(record.ts).ok_or_else(|| {
+                TickerError::InvalidArgument(format!("Timestamp out of range: {}", record.ts))
+            })?,
+        })
     }
 }
 
-impl TryFrom<String> for Exchange {
-    type Error = TickerError;
+impl Exchange {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        match value.as_str() {
-            "Binance" => Ok(Exchange::Binance),
-            "Bybit" => Ok(Exchange::Bybit),
-            "Coinbase" => Ok(Exchange::Coinbase),
-            _ => Err(TickerError::RawEventParseError(format!(
-                "Unknown exchange: {}",
-                value
-            ))),
-        }
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
@@ -100,26 +173,55 @@ impl TryFrom<String> for Pair {
     type Error = TickerError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        match value.as_str() {
-            "BTCUSDT" => Ok(Pair::BTCUSDT),
-            "ETHUSDT" => Ok(Pair::ETHUSDT),
-            "SOLUSDT" => Ok(Pair::SOLUSDT),
-            _ => Err(TickerError::RawEventParseError(format!(
-                "Unknown pair: {}",
-                value
-            ))),
+        value.parse()
+    }
+}
+
+impl FromStr for Pair {
+    type Err = TickerError;
+
+    /// Accepts `BASE-QUOTE`, `BASE/QUOTE`, `BASE_QUOTE`, or a concatenated
+    /// symbol such as `BTCUSDT` whose quote asset is one of [`Pair::KNOWN_QUOTES`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TickerError::RawEventParseError(format!("Invalid pair: {}", s));
+        let symbol = s.trim().to_uppercase();
+
+        if let Some((base, quote)) = symbol.split_once(['-', '/', '_']) {
+            if base.is_empty() || quote.is_empty() {
+                return Err(invalid());
+            }
+            return Ok(Pair::new(base, quote));
         }
+
+        Pair::KNOWN_QUOTES
+            .iter()
+            .find_map(|&quote| {
+                symbol
+                    .strip_suffix(quote)
+                    .filter(|base| !base.is_empty())
+                    .map(|base| Pair::new(base, quote))
+            })
+            .ok_or_else(invalid)
     }
 }
 
 impl Pair {
-    pub fn format(&self, format: PairFormat) -> String {
-        match self {
-            Pair::BTCUSDT => format.format("BTC", "USDT"),
-            Pair::ETHUSDT => format.format("ETH", "USDT"),
-            Pair::SOLUSDT => format.format("SOL", "USDT"),
+    /// Quote assets recognised when parsing a symbol without a separator,
+    /// longest first so `FDUSD` and `USDT` win over `USD`.
+    pub const KNOWN_QUOTES: &'static [&'static str] = &[
+        "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "USD", "EUR", "GBP", "BTC", "ETH", "BNB",
+    ];
+
+    pub fn new(base: impl Into<String>, quote: impl Into<String>) -> Self {
+        Self {
+            base: base.into().to_uppercase(),
+            quote: quote.into().to_uppercase(),
         }
     }
+
+    pub fn format(&self, format: PairFormat) -> String {
+        format.format(&self.base, &self.quote)
+    }
 }
 
 impl PairFormat {
@@ -137,24 +239,163 @@ impl PairFormat {
     }
 }
 
-impl std::fmt::Display for Exchange {
+impl FromStr for Side {
+    type Err = TickerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "buy" | "b" => Ok(Side::Buy),
+            "sell" | "s" => Ok(Side::Sell),
+            _ => Err(TickerError::RawEventParseError(format!(
+                "Unknown side: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Side {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Exchange::Binance => "Binance",
-            Exchange::Bybit => "Bybit",
-            Exchange::Coinbase => "Coinbase",
+        match self {
+            Side::Buy => write!(f, "buy"),
+            Side::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+impl Resolution {
+    pub fn from_millis(millis: i64) -> Result<Self, TickerError> {
+        if millis <= 0 {
+            return Err(TickerError::InvalidArgument(format!(
+                "Resolution must be positive, got {}ms",
+                millis
+            )));
+        }
+        Ok(Self { millis })
+    }
+
+    pub fn as_millis(&self) -> i64 {
+        self.millis
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_millis(self.millis as u64)
+    }
+
+    pub fn bucket_start(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        let millis = ts.timestamp_millis();
+        let start = millis - millis.rem_euclid(self.millis);
+        DateTime::from_timestamp_millis(start).unwrap_or(ts)
+    }
+
+    pub fn bucket_end(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        self.bucket_start(ts) + chrono::Duration::milliseconds(self.millis)
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = TickerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TickerError::InvalidArgument(format!("Invalid resolution: {}", s));
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (value, unit) = s.split_at(split);
+        let value: i64 = value.parse().map_err(|_| invalid())?;
+        let unit_millis = match unit {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60_000,
+            "h" => 3_600_000,
+            "d" => 86_400_000,
+            _ => return Err(invalid()),
         };
-        write!(f, "{}", s)
+        Resolution::from_millis(value.checked_mul(unit_millis).ok_or_else(invalid)?)
+    }
+}
+
+impl std::fmt::Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (value, unit) = [
+            (86_400_000, "d"),
+            (3_600_000, "h"),
+            (60_000, "m"),
+            (1_000, "s"),
+        ]
+        .into_iter()
+        .find(|(unit_millis, _)| self.millis % unit_millis == 0)
+        .map_or((self.millis, "ms"), |(unit_millis, unit)| {
+            (self.millis / unit_millis, unit)
+        });
+        write!(f, "{}{}", value, unit)
     }
 }
 
+impl Serialize for Resolution {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for Exchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Writes `BASE-QUOTE`, which is also how pairs are stored, so any pair
+/// parses back regardless of its quote asset.
 impl std::fmt::Display for Pair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Pair::BTCUSDT => "BTCUSDT",
-            Pair::ETHUSDT => "ETHUSDT",
-            Pair::SOLUSDT => "SOLUSDT",
-        };
-        write!(f, "{}", s)
+        write!(f, "{}", self.format(PairFormat::UpperWithDash))
+    }
+}
+
+impl Serialize for Pair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_display_parses_back() {
+        for pair in [
+            Pair::new("sol", "usdt"),
+            Pair::new("SOL", "DAI"),
+            Pair::new("ETH", "BTC"),
+            Pair::new("1000PEPE", "FDUSD"),
+        ] {
+            assert_eq!(pair.to_string().parse::<Pair>().unwrap(), pair);
+        }
+    }
+
+    #[test]
+    fn pair_parses_separators_and_known_quotes() {
+        let expected = Pair::new("BTC", "FDUSD");
+        for symbol in ["BTC-FDUSD", "btc/fdusd", "BTC_FDUSD", " BTCFDUSD "] {
+            assert_eq!(symbol.parse::<Pair>().unwrap(), expected);
+        }
+        assert!("SOLDAI".parse::<Pair>().is_err());
+        assert!("-USDT".parse::<Pair>().is_err());
+        assert!("USDT".parse::<Pair>().is_err());
+    }
+
+    #[test]
+    fn db_records_keep_unregistered_exchanges() {
+        let tick = PriceTick::try_from_db_record(PriceTickRecord {
+            exchange: "Consolidated".to_string(),
+            symbol: "SOL-USDT".to_string(),
+            price: 1.0,
+            sz: 1.0,
+            buy_sz: 0.0,
+            sell_sz: 0.0,
+            ts: 0,
+        })
+        .unwrap();
+        assert_eq!(tick.exchange, Exchange::new("Consolidated"));
     }
 }
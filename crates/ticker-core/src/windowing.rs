@@ -0,0 +1,215 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::types::{Event, Resolution};
+
+/// Buckets trades by their exchange timestamp and releases a bucket once the
+/// watermark has passed its end. The watermark trails the newest timestamp
+/// seen by the allowed lateness, and moves on with the local clock while no
+/// trades arrive.
+#[derive(Debug)]
+pub struct EventTimeWindows {
+    resolution: Resolution,
+    allowed_lateness: chrono::Duration,
+    buckets: BTreeMap<DateTime<Utc>, VecDeque<Event>>,
+    max_event_ts: Option<DateTime<Utc>>,
+    last_received: Option<DateTime<Utc>>,
+    closed_until: Option<DateTime<Utc>>,
+    len: usize,
+    late_events: u64,
+}
+
+impl EventTimeWindows {
+    pub fn new(resolution: Resolution, allowed_lateness: Duration) -> Self {
+        Self {
+            resolution,
+            allowed_lateness: chrono::Duration::from_std(allowed_lateness)
+                .unwrap_or(chrono::Duration::MAX),
+            buckets: BTreeMap::new(),
+            max_event_ts: None,
+            last_received: None,
+            closed_until: None,
+            len: 0,
+            late_events: 0,
+        }
+    }
+
+    /// Buffers a trade into its bucket. Returns `false` if the bucket was
+    /// already released, in which case the trade is counted as late and dropped.
+    /// Events without a timestamp are returned untouched.
+    pub fn push(&mut self, event: Event) -> Result<bool, Event> {
+        let Event::PriceTick(tick) = &event else {
+            return Err(event);
+        };
+
+        let start = self.resolution.bucket_start(tick.timestamp);
+        if self.closed_until.is_some_and(|closed| start < closed) {
+            self.late_events += 1;
+            return Ok(false);
+        }
+
+        self.max_event_ts = self.max_event_ts.max(Some(tick.timestamp));
+        self.last_received = self.last_received.max(Some(tick.received_at));
+        self.buckets.entry(start).or_default().push_back(event);
+        self.len += 1;
+        Ok(true)
+    }
+
+    /// Idle time is measured from the last arrival rather than compared with
+    /// exchange timestamps, so transport delay does not count as lateness.
+    pub fn watermark(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let event_time = match (self.max_event_ts, self.last_received) {
+            (Some(ts), Some(received)) => ts + (now - received).max(chrono::Duration::zero()),
+            _ => now,
+        };
+        event_time
+            .checked_sub_signed(self.allowed_lateness)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    /// Removes every bucket that ends at or before the watermark, oldest first.
+    pub fn drain_closed(&mut self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, Vec<Event>)> {
+        let watermark = self.watermark(now);
+        let open_from = self.resolution.bucket_start(watermark);
+        let open = self.buckets.split_off(&open_from);
+        let closed = std::mem::replace(&mut self.buckets, open);
+
+        self.closed_until = self.closed_until.max(Some(open_from));
+        self.len -= closed.values().map(VecDeque::len).sum::<usize>();
+        closed
+            .into_iter()
+            .map(|(start, events)| (start, events.into()))
+            .collect()
+    }
+
+    /// Removes every bucket regardless of the watermark.
+    pub fn drain_all(&mut self) -> Vec<(DateTime<Utc>, Vec<Event>)> {
+        self.closed_until = self.closed_until.max(
+            self.buckets
+                .keys()
+                .next_back()
+                .map(|start| self.resolution.bucket_end(*start)),
+        );
+        self.len = 0;
+        std::mem::take(&mut self.buckets)
+            .into_iter()
+            .map(|(start, events)| (start, events.into()))
+            .collect()
+    }
+
+    /// Removes the oldest event of the oldest bucket.
+    pub fn pop_oldest(&mut self) -> Option<Event> {
+        let mut oldest = self.buckets.first_entry()?;
+        let event = oldest.get_mut().pop_front()?;
+        if oldest.get().is_empty() {
+            oldest.remove();
+        }
+        self.len -= 1;
+        Some(event)
+    }
+
+    /// Rewrites the events of every open bucket with `f`, e.g. to fold them
+    /// into fewer events.
+    pub fn compact(&mut self, mut f: impl FnMut(Vec<Event>) -> Vec<Event>) {
+        for events in self.buckets.values_mut() {
+            *events = f(std::mem::take(events).into()).into();
+        }
+        self.buckets.retain(|_, events| !events.is_empty());
+        self.len = self.buckets.values().map(VecDeque::len).sum();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn late_events(&self) -> u64 {
+        self.late_events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::at, types::RawPriceTick};
+
+    fn trade(ts: i64, received: i64) -> Event {
+        Event::PriceTick(RawPriceTick {
+            received_at: at(received),
+            ..crate::test_util::trade(ts, 1.0, 1.0)
+        })
+    }
+
+    fn windows() -> EventTimeWindows {
+        EventTimeWindows::new(
+            Resolution::from_millis(1_000).unwrap(),
+            Duration::from_millis(500),
+        )
+    }
+
+    #[test]
+    fn releases_buckets_once_the_watermark_passes_them() {
+        let mut windows = windows();
+        for ts in [10_100, 10_900, 11_200] {
+            assert!(windows.push(trade(ts, ts + 50)).unwrap());
+        }
+        assert!(windows.drain_closed(at(11_250)).is_empty());
+
+        windows.push(trade(11_600, 11_650)).unwrap();
+        let closed = windows.drain_closed(at(11_650));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].0, at(10_000));
+        assert_eq!(closed[0].1.len(), 2);
+        assert_eq!(windows.len(), 2);
+    }
+
+    #[test]
+    fn drops_trades_for_closed_buckets_as_late() {
+        let mut windows = windows();
+        windows.push(trade(10_100, 10_150)).unwrap();
+        windows.push(trade(11_600, 11_650)).unwrap();
+        windows.drain_closed(at(11_650));
+
+        assert!(!windows.push(trade(10_800, 11_700)).unwrap());
+        assert!(windows.push(trade(11_100, 11_700)).unwrap());
+        assert_eq!(windows.late_events(), 1);
+        assert_eq!(windows.len(), 2);
+    }
+
+    #[test]
+    fn watermark_ignores_transport_delay_and_advances_while_idle() {
+        let mut windows = windows();
+        windows.push(trade(10_000, 12_000)).unwrap();
+        assert_eq!(windows.watermark(at(12_000)), at(9_500));
+        assert_eq!(windows.watermark(at(15_000)), at(12_500));
+    }
+
+    #[test]
+    fn unbounded_lateness_holds_every_bucket() {
+        let mut windows =
+            EventTimeWindows::new(Resolution::from_millis(1_000).unwrap(), Duration::MAX);
+        windows.push(trade(10_000, 10_000)).unwrap();
+        assert_eq!(windows.watermark(at(10_000)), DateTime::<Utc>::MIN_UTC);
+        assert!(windows.drain_closed(at(20_000)).is_empty());
+    }
+
+    #[test]
+    fn pop_oldest_takes_from_the_oldest_bucket() {
+        let mut windows = windows();
+        for ts in [11_100, 10_200, 10_300] {
+            windows.push(trade(ts, ts)).unwrap();
+        }
+        let Some(Event::PriceTick(oldest)) = windows.pop_oldest() else {
+            panic!("expected a trade");
+        };
+        assert_eq!(oldest.timestamp, at(10_200));
+        assert_eq!(windows.len(), 2);
+    }
+}
@@ -0,0 +1,2 @@
+ALTER TABLE price_ticks ADD COLUMN buy_sz REAL NOT NULL DEFAULT 0;
+ALTER TABLE price_ticks ADD COLUMN sell_sz REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS trades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    price REAL NOT NULL,
+    sz REAL NOT NULL,
+    side TEXT,
+    trade_id TEXT,
+    seq INTEGER,
+    ts INTEGER NOT NULL,
+    received_ts INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE INDEX IF NOT EXISTS idx_price_ticks_exchange_symbol_ts ON price_ticks (exchange, symbol, ts);
+CREATE INDEX IF NOT EXISTS idx_price_ticks_ts ON price_ticks (ts);
+CREATE INDEX IF NOT EXISTS idx_candles_exchange_symbol_ts ON candles (exchange, symbol, resolution_ms, ts);
+CREATE INDEX IF NOT EXISTS idx_trades_exchange_symbol_ts ON trades (exchange, symbol, ts);
@@ -0,0 +1,42 @@
+-- price_ticks.ts used to hold RFC 3339 text; store it as integer microseconds
+-- since the Unix epoch so range filters compare numerically.
+CREATE TABLE price_ticks_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    price REAL NOT NULL,
+    sz REAL NOT NULL,
+    ts INTEGER NOT NULL,
+    buy_sz REAL NOT NULL DEFAULT 0,
+    sell_sz REAL NOT NULL DEFAULT 0
+);
+
+INSERT INTO price_ticks_new (id, exchange, symbol, price, sz, ts, buy_sz, sell_sz)
+SELECT
+    id,
+    exchange,
+    symbol,
+    price,
+    sz,
+    CASE
+        WHEN typeof(ts) = 'integer' THEN ts
+        -- strftime only keeps milliseconds, so the fraction is read from the
+        -- text: the digits after the '.' and before any 'Z' / '+HH:MM' suffix.
+        ELSE CAST(strftime('%s', ts) AS INTEGER) * 1000000 + CASE
+            WHEN substr(ts, 20, 1) <> '.' THEN 0
+            ELSE CAST(substr(substr(ts, 21, length(ts) - 20 - CASE
+                WHEN ts LIKE '%Z' THEN 1
+                WHEN substr(ts, -6, 1) IN ('+', '-') THEN 6
+                ELSE 0
+            END) || '000000', 1, 6) AS INTEGER)
+        END
+    END,
+    buy_sz,
+    sell_sz
+FROM price_ticks;
+
+DROP TABLE price_ticks;
+ALTER TABLE price_ticks_new RENAME TO price_ticks;
+
+CREATE INDEX idx_price_ticks_exchange_symbol_ts ON price_ticks (exchange, symbol, ts);
+CREATE INDEX idx_price_ticks_ts ON price_ticks (ts);
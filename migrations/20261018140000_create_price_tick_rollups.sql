@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS price_tick_rollups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    resolution_ms INTEGER NOT NULL,
+    price REAL NOT NULL,
+    sz REAL NOT NULL,
+    buy_sz REAL NOT NULL,
+    sell_sz REAL NOT NULL,
+    ts INTEGER NOT NULL,
+    UNIQUE (exchange, symbol, resolution_ms, ts)
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_tick_rollups_resolution_ts ON price_tick_rollups (resolution_ms, ts);
@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS price_ticks (
+    id BIGSERIAL PRIMARY KEY,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sz DOUBLE PRECISION NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    buy_sz DOUBLE PRECISION NOT NULL DEFAULT 0,
+    sell_sz DOUBLE PRECISION NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS candles (
+    id BIGSERIAL PRIMARY KEY,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    resolution_ms BIGINT NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    open DOUBLE PRECISION NOT NULL,
+    high DOUBLE PRECISION NOT NULL,
+    low DOUBLE PRECISION NOT NULL,
+    close DOUBLE PRECISION NOT NULL,
+    volume DOUBLE PRECISION NOT NULL,
+    trade_count BIGINT NOT NULL,
+    vwap DOUBLE PRECISION NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS trades (
+    id BIGSERIAL PRIMARY KEY,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sz DOUBLE PRECISION NOT NULL,
+    side TEXT,
+    trade_id TEXT,
+    seq BIGINT,
+    ts TIMESTAMPTZ NOT NULL,
+    received_ts TIMESTAMPTZ NOT NULL
+);
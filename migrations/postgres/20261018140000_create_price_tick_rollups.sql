@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS price_tick_rollups (
+    id BIGSERIAL PRIMARY KEY,
+    exchange TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    resolution_ms BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    sz DOUBLE PRECISION NOT NULL,
+    buy_sz DOUBLE PRECISION NOT NULL,
+    sell_sz DOUBLE PRECISION NOT NULL,
+    ts TIMESTAMPTZ NOT NULL,
+    UNIQUE (exchange, symbol, resolution_ms, ts)
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_tick_rollups_resolution_ts ON price_tick_rollups (resolution_ms, ts);